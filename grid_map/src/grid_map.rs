@@ -42,6 +42,19 @@ impl GridPositionConverter {
             size,
         }
     }
+    /// Create grid position converter with the number of the cells
+    fn new_with_size(min_point: Position, size: Size, resolution: f64) -> Self {
+        let max_point = Position::new(
+            min_point.x + size.width as f64 * resolution,
+            min_point.y + size.height as f64 * resolution,
+        );
+        Self {
+            resolution,
+            min_point,
+            max_point,
+            size,
+        }
+    }
     fn resolution(&self) -> f64 {
         self.resolution
     }
@@ -102,6 +115,19 @@ where
         }
     }
 
+    /// Create GridMap with the number of the cells
+    ///
+    /// Unlike [`GridMap::new`], the size is not affected by the rounding error of the max point.
+    pub fn new_with_size(min_point: Position, size: Size, resolution: f64) -> Self {
+        assert!(!size.is_empty());
+        let grid_converter = GridPositionConverter::new_with_size(min_point, size, resolution);
+        let cells = vec![Cell::Uninitialized; grid_converter.size().len()];
+        GridMap {
+            grid_converter,
+            cells,
+        }
+    }
+
     /// Convert the grid into the index of the cells
     fn to_index(&self, grid: &Grid) -> Option<usize> {
         self.grid_converter.to_index(grid)
@@ -114,6 +140,17 @@ where
         self.grid_converter.to_grid(&Position::new(x, y))
     }
 
+    /// Convert grid into the position of the center of the cell
+    pub fn to_position(&self, grid: &Grid) -> Option<Position> {
+        self.to_index(grid)?;
+        let min_point = self.min_point();
+        let resolution = self.resolution();
+        Some(Position::new(
+            min_point.x + (grid.x as f64 + 0.5) * resolution,
+            min_point.y + (grid.y as f64 + 0.5) * resolution,
+        ))
+    }
+
     /// Get cell by grid if it is inside of the map
     pub fn cell(&self, grid: &Grid) -> Option<&Cell<T>> {
         self.to_index(grid).map(|index| &self.cells[index])
@@ -194,6 +231,98 @@ where
         Some(())
     }
 
+    /// Grids which the line segment from `start` to `end` passes through, in order
+    ///
+    /// Both of the cells of `start` and `end` are included, and the segment is clipped by the map,
+    /// so the grids out of the map are never walked.
+    pub fn raytrace(&self, start: &Position, end: &Position) -> Vec<Grid> {
+        if ![start.x, start.y, end.x, end.y]
+            .iter()
+            .all(|v| v.is_finite())
+        {
+            return vec![];
+        }
+        let min_point = self.min_point();
+        let resolution = self.resolution();
+        // positions in the unit of the cells
+        let (start_x, start_y) = (
+            (start.x - min_point.x) / resolution,
+            (start.y - min_point.y) / resolution,
+        );
+        let (end_x, end_y) = (
+            (end.x - min_point.x) / resolution,
+            (end.y - min_point.y) / resolution,
+        );
+        let (width, height) = (self.width() as f64, self.height() as f64);
+        let (dx, dy) = (end_x - start_x, end_y - start_y);
+        if (dx == 0.0 && !(0.0..width).contains(&start_x))
+            || (dy == 0.0 && !(0.0..height).contains(&start_y))
+        {
+            return vec![];
+        }
+        // clip the segment by the map (Liang-Barsky), parameterized from 0 (start) to 1 (end)
+        let (mut s_min, mut s_max) = (0.0_f64, 1.0_f64);
+        for (p, q) in [
+            (-dx, start_x),
+            (dx, width - start_x),
+            (-dy, start_y),
+            (dy, height - start_y),
+        ] {
+            if p < 0.0 {
+                s_min = s_min.max(q / p);
+            } else if p > 0.0 {
+                s_max = s_max.min(q / p);
+            }
+        }
+        // the segment only touching the border is out of the map
+        if s_min > s_max || (s_min == s_max && (dx != 0.0 || dy != 0.0)) {
+            return vec![];
+        }
+        let (start_x, start_y) = (start_x + s_min * dx, start_y + s_min * dy);
+        let (dx, dy) = ((s_max - s_min) * dx, (s_max - s_min) * dy);
+        let (end_x, end_y) = (start_x + dx, start_y + dy);
+        // the clipped points can be on the upper borders or out of the map by the rounding error
+        let to_cell = |v: f64, len: usize| (v.floor() as i64).clamp(0, len as i64 - 1);
+        let mut x = to_cell(start_x, self.width());
+        let mut y = to_cell(start_y, self.height());
+        let last_x = to_cell(end_x, self.width());
+        let last_y = to_cell(end_y, self.height());
+        let step_x = if dx > 0.0 { 1 } else { -1 };
+        let step_y = if dy > 0.0 { 1 } else { -1 };
+        // ray parameters to cross the next cell borders, and to cross a cell
+        let (mut t_max_x, t_delta_x) = if dx == 0.0 {
+            (f64::INFINITY, f64::INFINITY)
+        } else if dx > 0.0 {
+            ((x as f64 + 1.0 - start_x) / dx, 1.0 / dx)
+        } else {
+            ((start_x - x as f64) / -dx, -1.0 / dx)
+        };
+        let (mut t_max_y, t_delta_y) = if dy == 0.0 {
+            (f64::INFINITY, f64::INFINITY)
+        } else if dy > 0.0 {
+            ((y as f64 + 1.0 - start_y) / dy, 1.0 / dy)
+        } else {
+            ((start_y - y as f64) / -dy, -1.0 / dy)
+        };
+        let num_steps = (last_x - x).abs() + (last_y - y).abs();
+        let mut grids = Vec::with_capacity(num_steps as usize + 1);
+        for i in 0..=num_steps {
+            grids.push(Grid::new(x as usize, y as usize));
+            if i == num_steps {
+                break;
+            }
+            // never go beyond the last cell by the rounding error
+            if y == last_y || (x != last_x && t_max_x < t_max_y) {
+                x += step_x;
+                t_max_x += t_delta_x;
+            } else {
+                y += step_y;
+                t_max_y += t_delta_y;
+            }
+        }
+        grids
+    }
+
     /// Copy the map, but un-initialize the Value cells with Uninitialized.
     pub fn copy_without_value(&self) -> Self {
         let cells: Vec<_> = self
@@ -241,4 +370,88 @@ mod tests {
             Cell::Value(1.0)
         );
     }
+
+    #[test]
+    fn test_new_with_size() {
+        let map = GridMap::<u8>::new_with_size(Position::new(0.0, 0.0), Size::new(3, 2), 0.1);
+        assert_eq!(map.width(), 3);
+        assert_eq!(map.height(), 2);
+        assert_eq!(map.len(), 6);
+        assert!((map.max_point().x - 0.3).abs() < 1e-9);
+        assert_eq!(map.to_grid(0.25, 0.15), Some(Grid::new(2, 1)));
+        assert!(map.to_grid(0.35, 0.15).is_none());
+    }
+
+    #[test]
+    fn test_to_position() {
+        let map = GridMap::<u8>::new_with_size(Position::new(-1.0, 0.0), Size::new(3, 2), 0.1);
+        let position = map.to_position(&Grid::new(2, 1)).unwrap();
+        assert!((position.x + 0.75).abs() < 1e-9);
+        assert!((position.y - 0.15).abs() < 1e-9);
+        assert_eq!(map.to_grid(position.x, position.y), Some(Grid::new(2, 1)));
+        assert!(map.to_position(&Grid::new(3, 0)).is_none());
+    }
+
+    #[test]
+    fn test_raytrace() {
+        let map = GridMap::<u8>::new_with_size(Position::new(0.0, 0.0), Size::new(10, 10), 1.0);
+        let grids = map.raytrace(&Position::new(0.5, 0.5), &Position::new(3.5, 0.5));
+        assert_eq!(grids, (0..4).map(|x| Grid::new(x, 0)).collect::<Vec<_>>());
+        let grids = map.raytrace(&Position::new(0.5, 0.5), &Position::new(2.5, 1.7));
+        assert_eq!(
+            grids,
+            vec![
+                Grid::new(0, 0),
+                Grid::new(1, 0),
+                Grid::new(1, 1),
+                Grid::new(2, 1)
+            ]
+        );
+        // backward and steep
+        let grids = map.raytrace(&Position::new(5.5, 9.5), &Position::new(4.2, 2.5));
+        assert_eq!(grids.first(), Some(&Grid::new(5, 9)));
+        assert_eq!(grids.last(), Some(&Grid::new(4, 2)));
+        assert_eq!(grids.len(), 9);
+        for pair in grids.windows(2) {
+            assert_eq!(
+                pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y),
+                1
+            );
+        }
+        // clipped by the map
+        let grids = map.raytrace(&Position::new(-2.5, 0.5), &Position::new(1.5, 0.5));
+        assert_eq!(grids, vec![Grid::new(0, 0), Grid::new(1, 0)]);
+        let grids = map.raytrace(&Position::new(8.5, 8.5), &Position::new(12.5, 8.5));
+        assert_eq!(grids, vec![Grid::new(8, 8), Grid::new(9, 8)]);
+        assert_eq!(
+            map.raytrace(&Position::new(3.5, 3.5), &Position::new(3.6, 3.6)),
+            vec![Grid::new(3, 3)]
+        );
+        assert!(map
+            .raytrace(&Position::new(f64::NAN, 0.0), &Position::new(1.0, 1.0))
+            .is_empty());
+        // far out of the map
+        let grids = map.raytrace(&Position::new(0.5, 0.5), &Position::new(1e12, 0.5));
+        assert_eq!(grids, (0..10).map(|x| Grid::new(x, 0)).collect::<Vec<_>>());
+        let grids = map.raytrace(&Position::new(-1e12, 5.5), &Position::new(1e12, 5.5));
+        assert_eq!(grids, (0..10).map(|x| Grid::new(x, 5)).collect::<Vec<_>>());
+        let grids = map.raytrace(&Position::new(0.5, 0.5), &Position::new(1e12, 1e12));
+        assert_eq!(grids.first(), Some(&Grid::new(0, 0)));
+        assert_eq!(grids.last(), Some(&Grid::new(9, 9)));
+        assert_eq!(grids.len(), 19);
+        let grids = map.raytrace(&Position::new(5.5, 1e12), &Position::new(5.5, -1e12));
+        assert_eq!(
+            grids,
+            (0..10).rev().map(|y| Grid::new(5, y)).collect::<Vec<_>>()
+        );
+        assert!(map
+            .raytrace(&Position::new(-1e12, 20.5), &Position::new(1e12, 20.5))
+            .is_empty());
+        assert!(map
+            .raytrace(&Position::new(10.0, 0.5), &Position::new(10.0, 5.5))
+            .is_empty());
+        assert!(map
+            .raytrace(&Position::new(9.0, 11.0), &Position::new(11.0, 9.0))
+            .is_empty());
+    }
 }
@@ -3,6 +3,7 @@ mod error;
 mod grid;
 mod grid_map;
 mod layered_grid_map;
+mod occupancy_grid_map;
 mod position;
 pub mod utils;
 pub use crate::cell::*;
@@ -10,4 +11,5 @@ pub use crate::error::*;
 pub use crate::grid::*;
 pub use crate::grid_map::*;
 pub use crate::layered_grid_map::*;
+pub use crate::occupancy_grid_map::*;
 pub use crate::position::*;
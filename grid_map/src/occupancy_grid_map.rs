@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Cell, Error, Grid, GridMap, Position, Result, Size};
+
+/// Parameters of the inverse sensor model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct OccupancyConfig {
+    /// probability of the cell at the end of the beam being occupied
+    pub probability_hit: f64,
+    /// probability of the cell on the beam being occupied
+    pub probability_miss: f64,
+    /// lower clamp of the probability, so that the cell can be changed quickly
+    pub probability_min: f64,
+    /// upper clamp of the probability
+    pub probability_max: f64,
+    /// cells with the probability larger than or equal to it are obstacles
+    pub occupied_threshold: f64,
+}
+
+impl Default for OccupancyConfig {
+    fn default() -> Self {
+        Self {
+            probability_hit: 0.7,
+            probability_miss: 0.4,
+            probability_min: 0.12,
+            probability_max: 0.97,
+            occupied_threshold: 0.65,
+        }
+    }
+}
+
+impl OccupancyConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("probability_hit", self.probability_hit),
+            ("probability_miss", self.probability_miss),
+            ("probability_min", self.probability_min),
+            ("probability_max", self.probability_max),
+            ("occupied_threshold", self.occupied_threshold),
+        ] {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(Error::Other(format!(
+                    "{name} must be in (0, 1), but {value}"
+                )));
+            }
+        }
+        if self.probability_hit <= 0.5 || self.probability_miss >= 0.5 {
+            return Err(Error::Other(
+                "probability_hit must be > 0.5 and probability_miss must be < 0.5".to_owned(),
+            ));
+        }
+        if self.probability_min >= self.probability_max {
+            return Err(Error::Other(
+                "probability_min must be < probability_max".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Occupancy grid map which stores the log-odds of the cells
+///
+/// The cells which have never been observed are `Cell::Uninitialized`.
+#[derive(Debug, Clone)]
+pub struct OccupancyGridMap {
+    config: OccupancyConfig,
+    map: GridMap<f64>,
+    log_odds_hit: f64,
+    log_odds_miss: f64,
+    log_odds_min: f64,
+    log_odds_max: f64,
+}
+
+impl OccupancyGridMap {
+    pub fn new(
+        min_point: Position,
+        max_point: Position,
+        resolution: f64,
+        config: OccupancyConfig,
+    ) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            map: GridMap::new(min_point, max_point, resolution),
+            log_odds_hit: log_odds(config.probability_hit),
+            log_odds_miss: log_odds(config.probability_miss),
+            log_odds_min: log_odds(config.probability_min),
+            log_odds_max: log_odds(config.probability_max),
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &OccupancyConfig {
+        &self.config
+    }
+
+    /// The log-odds of the cells
+    pub fn log_odds_map(&self) -> &GridMap<f64> {
+        &self.map
+    }
+
+    /// Probability of the cell being occupied, or `None` if it has not been observed
+    pub fn probability(&self, grid: &Grid) -> Option<f64> {
+        self.map.value(grid).map(probability)
+    }
+
+    /// Update the cells on the beam from the sensor
+    ///
+    /// The cells between the sensor and `end` become free. The cell of `end` becomes occupied if
+    /// `hit`, otherwise free, e.g. for the beam without return which is cut at the max range.
+    pub fn insert_beam(&mut self, sensor: &Position, end: &Position, hit: bool) {
+        let grids = self.map.raytrace(sensor, end);
+        let end_grid = self.map.to_grid(end.x, end.y);
+        for grid in grids {
+            let delta = if hit && Some(grid) == end_grid {
+                self.log_odds_hit
+            } else {
+                self.log_odds_miss
+            };
+            self.update_cell(&grid, delta);
+        }
+    }
+
+    /// Update the cells by the hit points of a scan from the sensor
+    pub fn insert_scan(&mut self, sensor: &Position, hits: impl IntoIterator<Item = Position>) {
+        for hit in hits {
+            self.insert_beam(sensor, &hit, true);
+        }
+    }
+
+    /// Update the cells by the ranges of a laser scan
+    ///
+    /// The beams without return, which are infinity or not less than `range_max`, clear the cells
+    /// up to `range_max`. `NaN` ranges are ignored.
+    pub fn insert_ranges(
+        &mut self,
+        sensor: &Position,
+        sensor_yaw: f64,
+        angle_min: f64,
+        angle_increment: f64,
+        ranges: &[f64],
+        range_max: f64,
+    ) {
+        for (i, range) in ranges.iter().enumerate() {
+            if range.is_nan() {
+                continue;
+            }
+            let hit = *range < range_max;
+            let range = range.min(range_max);
+            let angle = sensor_yaw + angle_min + angle_increment * i as f64;
+            let end = Position::new(
+                sensor.x + range * angle.cos(),
+                sensor.y + range * angle.sin(),
+            );
+            self.insert_beam(sensor, &end, hit);
+        }
+    }
+
+    fn update_cell(&mut self, grid: &Grid, delta: f64) {
+        if let Some(cell) = self.map.cell_mut(grid) {
+            let current = cell.value().copied().unwrap_or(0.0);
+            *cell = Cell::Value((current + delta).clamp(self.log_odds_min, self.log_odds_max));
+        }
+    }
+
+    /// Convert into the map for the cost maps
+    ///
+    /// The occupied cells are `Cell::Obstacle`, the unobserved cells are `Cell::Unknown`, and the
+    /// others are `Cell::Value` of the probability in percent.
+    pub fn to_grid_map(&self) -> GridMap<u8> {
+        let mut map = GridMap::new_with_size(
+            self.map.min_point().to_owned(),
+            Size::new(self.map.width(), self.map.height()),
+            self.map.resolution(),
+        );
+        for (cell, log_odds) in map.cells_mut().iter_mut().zip(self.map.cells()) {
+            *cell = match log_odds {
+                Cell::Value(l) => {
+                    let p = probability(*l);
+                    if p >= self.config.occupied_threshold {
+                        Cell::Obstacle
+                    } else {
+                        Cell::Value((p * 100.0).round() as u8)
+                    }
+                }
+                _ => Cell::Unknown,
+            };
+        }
+        map
+    }
+}
+
+fn log_odds(probability: f64) -> f64 {
+    (probability / (1.0 - probability)).ln()
+}
+
+fn probability(log_odds: f64) -> f64 {
+    1.0 - 1.0 / (1.0 + log_odds.exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occupancy_grid_map_test() {
+        let mut map = OccupancyGridMap::new(
+            Position::new(0.0, 0.0),
+            Position::new(1.0, 1.0),
+            0.1,
+            OccupancyConfig::default(),
+        )
+        .unwrap();
+        let sensor = Position::new(0.05, 0.05);
+        map.insert_scan(&sensor, [Position::new(0.55, 0.05)]);
+        let miss = map.probability(&Grid::new(2, 0)).unwrap();
+        assert!((miss - 0.4).abs() < 1e-9);
+        let hit = map.probability(&Grid::new(5, 0)).unwrap();
+        assert!((hit - 0.7).abs() < 1e-9);
+        assert_eq!(map.probability(&Grid::new(6, 0)), None);
+
+        let grid_map = map.to_grid_map();
+        assert_eq!(grid_map.cell(&Grid::new(5, 0)), Some(&Cell::Obstacle));
+        assert_eq!(grid_map.cell(&Grid::new(2, 0)), Some(&Cell::Value(40)));
+        assert_eq!(grid_map.cell(&Grid::new(6, 0)), Some(&Cell::Unknown));
+
+        // clamped
+        for _ in 0..20 {
+            map.insert_scan(&sensor, [Position::new(0.55, 0.05)]);
+        }
+        assert!((map.probability(&Grid::new(5, 0)).unwrap() - 0.97).abs() < 1e-9);
+        assert!((map.probability(&Grid::new(2, 0)).unwrap() - 0.12).abs() < 1e-9);
+
+        // the obstacle has gone
+        for _ in 0..8 {
+            map.insert_beam(&sensor, &Position::new(0.95, 0.05), false);
+        }
+        assert!(map.probability(&Grid::new(5, 0)).unwrap() < 0.65);
+        assert!(map
+            .to_grid_map()
+            .cell(&Grid::new(5, 0))
+            .unwrap()
+            .has_value());
+        assert!(map
+            .to_grid_map()
+            .cell(&Grid::new(9, 0))
+            .unwrap()
+            .has_value());
+    }
+
+    #[test]
+    fn insert_ranges_test() {
+        let mut map = OccupancyGridMap::new(
+            Position::new(0.0, 0.0),
+            Position::new(1.0, 1.0),
+            0.1,
+            OccupancyConfig::default(),
+        )
+        .unwrap();
+        // facing +y, the beams to +x, +y and -x
+        map.insert_ranges(
+            &Position::new(0.55, 0.55),
+            std::f64::consts::FRAC_PI_2,
+            -std::f64::consts::FRAC_PI_2,
+            std::f64::consts::FRAC_PI_2,
+            &[0.3, f64::INFINITY, f64::NAN],
+            0.4,
+        );
+        let grid_map = map.to_grid_map();
+        assert_eq!(grid_map.cell(&Grid::new(8, 5)), Some(&Cell::Obstacle));
+        assert!(grid_map.cell(&Grid::new(5, 9)).unwrap().has_value());
+        assert_eq!(grid_map.cell(&Grid::new(5, 10)), None);
+        assert_eq!(grid_map.cell(&Grid::new(4, 5)), Some(&Cell::Unknown));
+    }
+
+    #[test]
+    fn occupancy_config_test() {
+        assert!(OccupancyConfig::default().validate().is_ok());
+        let config = OccupancyConfig {
+            probability_hit: 0.3,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = OccupancyConfig {
+            probability_min: 0.99,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config: OccupancyConfig = serde_yaml::from_str("probability_hit: 0.8").unwrap();
+        assert_eq!(config.probability_hit, 0.8);
+        assert_eq!(config.probability_miss, 0.4);
+    }
+}
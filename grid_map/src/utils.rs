@@ -13,19 +13,85 @@ struct Pgm {
     path: String,
     origin: [f64; 3],
     resolution: f64,
+    #[serde(default)]
+    negate: u8,
+    #[serde(default = "default_occupied_thresh")]
+    occupied_thresh: f64,
+    #[serde(default = "default_free_thresh")]
+    free_thresh: f64,
 }
 
+fn default_occupied_thresh() -> f64 {
+    0.65
+}
+
+fn default_free_thresh() -> f64 {
+    0.196
+}
+
+/// Load the raw pixel values of the map in the format of ROS `map_server`
+///
+/// The rows of the image are used in order, so the top row is the min y and the map is mirrored
+/// in y from the one of ROS. The image path is relative to the current directory.
+/// Use [`load_ros_occupancy_yaml`] to load the map in the same orientation as ROS.
 pub fn load_ros_yaml<P: AsRef<Path>>(yaml_path: P) -> Result<GridMap<u8>, Error> {
     let yaml_str = std::fs::read_to_string(yaml_path)?;
     let Pgm {
         path,
         origin,
         resolution,
+        ..
     } = serde_yaml::from_str(&yaml_str)?;
     let origin = Position::new(origin[0], origin[1]);
     load_pgm(path, origin, resolution)
 }
 
+/// Load the map in the format of ROS `map_server` as an occupancy map
+///
+/// The pixels are classified by `occupied_thresh` and `free_thresh`:
+/// the occupied cells are `Cell::Obstacle`, the free cells are `Cell::Value` of the occupancy
+/// in percent, and the others are `Cell::Unknown`. The image path is relative to the yaml file
+/// (or the current directory), and the top row of the image is the max y as in ROS, unlike
+/// [`load_ros_yaml`].
+pub fn load_ros_occupancy_yaml<P: AsRef<Path>>(yaml_path: P) -> Result<GridMap<u8>, Error> {
+    let yaml_path = yaml_path.as_ref();
+    let yaml_str = std::fs::read_to_string(yaml_path)?;
+    let pgm: Pgm = serde_yaml::from_str(&yaml_str)?;
+    let mut image_path = Path::new(&pgm.path).to_path_buf();
+    if image_path.is_relative() {
+        let relative_to_yaml = yaml_path
+            .parent()
+            .map(|dir| dir.join(&image_path))
+            .unwrap_or_default();
+        if relative_to_yaml.exists() {
+            image_path = relative_to_yaml;
+        }
+    }
+    let origin = Position::new(pgm.origin[0], pgm.origin[1]);
+    let pixels = load_pgm(image_path, origin, pgm.resolution)?;
+    let mut map = pixels.copy_without_value();
+    let width = pixels.width();
+    for (i, cell) in pixels.cells().iter().enumerate() {
+        let Some(pixel) = cell.value() else {
+            continue;
+        };
+        let occupancy = if pgm.negate == 0 {
+            (255.0 - *pixel as f64) / 255.0
+        } else {
+            *pixel as f64 / 255.0
+        };
+        let flipped = (pixels.height() - 1 - i / width) * width + i % width;
+        map.cells_mut()[flipped] = if occupancy > pgm.occupied_thresh {
+            Cell::Obstacle
+        } else if occupancy < pgm.free_thresh {
+            Cell::Value((occupancy * 100.0).round() as u8)
+        } else {
+            Cell::Unknown
+        };
+    }
+    Ok(map)
+}
+
 pub fn load_pgm<P: AsRef<Path>>(
     path: P,
     origin: Position,
@@ -91,4 +157,31 @@ mod tests {
             assert!(cell.value().is_some());
         }
     }
+
+    #[test]
+    fn load_ros_occupancy_file() {
+        let map = load_ros_occupancy_yaml("test/map.yaml").unwrap();
+        assert_eq!(map.width(), 194);
+        assert_eq!(map.height(), 170);
+        let num_wall = map.cells().iter().filter(|c| c.is_obstacle()).count();
+        let num_unknown = map
+            .cells()
+            .iter()
+            .filter(|c| matches!(c, Cell::Unknown))
+            .count();
+        assert_eq!(num_wall, 1335);
+        assert_eq!(num_unknown, 16273);
+        // the top row of the image is the max y
+        let raw = load_ros_yaml("test/map.yaml").unwrap();
+        assert_eq!(
+            map.cells()[..map.width()]
+                .iter()
+                .filter(|c| c.is_obstacle())
+                .count(),
+            raw.cells()[raw.cells().len() - raw.width()..]
+                .iter()
+                .filter(|c| c.value() == Some(&0))
+                .count()
+        );
+    }
 }
@@ -1,10 +1,10 @@
 // How to run:
 //
 // ```sh
-// # start viewer
-// cargo run --release -p openrr-nav-viewer
+// # start viewer, which loads and saves the planner in the navigation config
+// cargo run --release -p openrr-nav-viewer -- -n openrr-nav/config/navigation_config.yaml
 // # start controller example
-// cargo run --release -p openrr-nav-viewer --example controller -- -f openrr-nav/config/dwa_parameter_config.yaml
+// cargo run --release -p openrr-nav-viewer --example controller -- -n openrr-nav/config/navigation_config.yaml
 // ```
 
 mod shared;
@@ -12,7 +12,10 @@ mod shared;
 use anyhow::Result;
 use clap::Parser;
 use grid_map::*;
-use openrr_nav::{utils::nearest_path_point, *};
+use openrr_nav::{
+    utils::{look_ahead_path_point, nearest_path_point, normalize_angle},
+    *,
+};
 use openrr_nav_viewer::*;
 use rand::distributions::{Distribution, Uniform};
 use shared::*;
@@ -22,18 +25,17 @@ const ENDPOINT: &str = "http://[::1]:50101";
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    let config = args.navigation_config()?;
+    // the planner is owned by the viewer, which saves the tuned one to its config file
     let mut api = pb::api_client::ApiClient::connect(ENDPOINT).await?;
-    api.set_config(pb::Config {
-        text: std::fs::read_to_string(&args.planner_config_path)?,
-    })
-    .await?;
     loop {
-        controller(&mut api).await?
+        controller(&mut api, &config).await?
     }
 }
 
 async fn controller(
     api: &mut openrr_nav_viewer::pb::api_client::ApiClient<tonic::transport::Channel>,
+    config: &NavigationConfig,
 ) -> Result<()> {
     if !api.get_is_run(()).await?.into_inner() {
         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
@@ -60,7 +62,7 @@ async fn controller(
             Cell::Obstacle
         )
     };
-    const EXTEND_LENGTH: f64 = 0.05;
+    let GlobalPlannerConfig::Rrt(rrt_config) = &config.global_planner;
     let mut result = rrt::dual_rrt_connect(
         &[start[0], start[1]],
         &[goal[0], goal[1]],
@@ -69,12 +71,17 @@ async fn controller(
             let mut rng = rand::thread_rng();
             vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
         },
-        EXTEND_LENGTH,
-        4000,
+        rrt_config.extend_length,
+        rrt_config.max_iterations,
     )
     .unwrap();
-    rrt::smooth_path(&mut result, is_free, EXTEND_LENGTH, 1000);
-    let result = linear_interpolate_path(result, EXTEND_LENGTH);
+    rrt::smooth_path(
+        &mut result,
+        is_free,
+        rrt_config.extend_length,
+        rrt_config.smooth_iterations,
+    );
+    let result = linear_interpolate_path(result, rrt_config.extend_length);
     let result =
         add_target_position_to_path(result, &Pose::new(Vector2::new(goal[0], goal[1]), goal[2]));
     api.set_global_path(pb::RobotPath::from(robot_path_from_vec_vec(result.clone())))
@@ -95,8 +102,13 @@ async fn controller(
 
     let obstacle_distance_map = obstacle_distance_map(&map).unwrap();
 
-    let local_goal_distance_map =
-        local_goal_distance_map(&map, &result, [start[0], start[1]]).unwrap();
+    let local_goal_distance_map = local_goal_distance_map(
+        &map,
+        &result,
+        [start[0], start[1]],
+        &config.cost_maps.local_goal,
+    )
+    .unwrap();
 
     api.set_layered_grid_map(pb::SetLayeredGridMapRequest {
         maps: vec![
@@ -141,23 +153,40 @@ async fn controller(
     let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), start[2]);
     let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), goal[2]);
 
-    let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
+    let mut current_velocity = Velocity::default();
     let mut plan_map = map.clone();
 
-    for i in 0..300 {
-        // let dynamic_map = new_dynamic_sample_map(i);
-        let dynamic_map = new_sample_map();
-        let path_distance_map = openrr_nav::path_distance_map(&dynamic_map, &path_grid).unwrap();
+    let mut last_dynamic_map = new_sample_map();
+    let mut path_distance =
+        IncrementalDistanceMap::path_distance(&last_dynamic_map, &path_grid).unwrap();
+    let mut goal_distance =
+        IncrementalDistanceMap::goal_distance(&last_dynamic_map, &goal_grid).unwrap();
+    let mut obstacle_distance =
+        IncrementalDistanceMap::obstacle_distance(&last_dynamic_map).unwrap();
 
-        let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
-        let goal_distance_map = openrr_nav::goal_distance_map(&dynamic_map, &goal_grid).unwrap();
-
-        let obstacle_distance_map = openrr_nav::obstacle_distance_map(&dynamic_map).unwrap();
+    for i in 0..300 {
+        let dynamic_map = new_dynamic_sample_map(i);
+        // repair only around the changed cells
+        let changed = changed_grids(&last_dynamic_map, &dynamic_map);
+        for distance_map in [
+            &mut path_distance,
+            &mut goal_distance,
+            &mut obstacle_distance,
+        ] {
+            distance_map
+                .update(&dynamic_map, changed.iter().copied())
+                .unwrap();
+        }
+        last_dynamic_map = dynamic_map;
+        let path_distance_map = path_distance.map().clone();
+        let goal_distance_map = goal_distance.map().clone();
+        let obstacle_distance_map = obstacle_distance.map().clone();
 
         let local_goal_distance_map = openrr_nav::local_goal_distance_map(
             &map,
             &result,
             [current_pose.translation.x, current_pose.translation.y],
+            &config.cost_maps.local_goal,
         )
         .unwrap();
 
@@ -188,14 +217,13 @@ async fn controller(
                 &result,
                 [current_pose.translation.x, current_pose.translation.y],
             );
-            let len = result.len();
             let mut table = vec![pb::NamedAngle {
                 name: ROTATION_COST_NAME.to_owned(),
                 angle: current_pose.rotation.angle(),
             }];
-            const FORWARD_OFFSET: usize = 20;
             if let Some((idx, _)) = nearest_path_point {
-                let look_ahead_idx = (idx + FORWARD_OFFSET).min(len - 1);
+                let look_ahead_idx =
+                    look_ahead_path_point(&result, idx, config.path_look_ahead_distance);
                 table.push(pb::NamedAngle {
                     name: PATH_DIRECTION_COST_NAME.to_owned(),
                     angle: result[look_ahead_idx][2],
@@ -214,7 +242,7 @@ async fn controller(
                 })
                 .await?
                 .into_inner(),
-                api.predicted_plan_candidates(pb::PlanRequest {
+                api.scored_plan_candidates(pb::PlanRequest {
                     current_pose: Some(current_pose.into()),
                     current_velocity: Some(current_velocity.into()),
                 })
@@ -241,7 +269,7 @@ async fn controller(
 
         api.set_current_pose(pb::Isometry2::from(current_pose))
             .await?;
-        std::thread::sleep(std::time::Duration::from_millis(50));
+        std::thread::sleep(config.controller_period());
 
         if let Some(grid) = plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
         {
@@ -250,12 +278,10 @@ async fn controller(
             println!("OUT OF MAP!");
             return Ok(());
         }
-        const GOAL_THRESHOLD_DISTANCE: f64 = 0.1;
-        const GOAL_THRESHOLD_ANGLE_DIFFERENCE: f64 = 0.4;
         if (goal_pose.translation.vector - current_pose.translation.vector).norm()
-            < GOAL_THRESHOLD_DISTANCE
-            && (goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
-                < GOAL_THRESHOLD_ANGLE_DIFFERENCE
+            < config.goal_tolerance.distance
+            && normalize_angle(goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
+                < config.goal_tolerance.angle
         {
             println!("GOAL! count = {i}");
             break;
@@ -264,3 +290,10 @@ async fn controller(
     api.set_is_run(false).await?;
     Ok(())
 }
+
+fn changed_grids(old: &GridMap<u8>, new: &GridMap<u8>) -> Vec<Grid> {
+    (0..new.height())
+        .flat_map(|y| (0..new.width()).map(move |x| Grid::new(x, y)))
+        .filter(|grid| old.cell(grid) != new.cell(grid))
+        .collect()
+}
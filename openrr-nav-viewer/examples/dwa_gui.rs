@@ -8,27 +8,21 @@ mod shared;
 
 use clap::Parser;
 use grid_map::*;
-use openrr_nav::{utils::nearest_path_point, *};
+use openrr_nav::{
+    utils::{look_ahead_path_point, nearest_path_point, normalize_angle},
+    *,
+};
 use openrr_nav_viewer::*;
 use rand::distributions::{Distribution, Uniform};
 use shared::*;
 
 fn main() {
-    let nav: NavigationViz = Args::parse().try_into().unwrap();
+    let args = Args::parse();
+    let config = args.navigation_config().unwrap();
+    let nav: NavigationViz = args.try_into().unwrap();
 
     let cloned_nav = nav.clone();
 
-    let planner = DwaPlanner::new_from_config(format!(
-        "{}/../openrr-nav/config/dwa_parameter_config.yaml",
-        env!("CARGO_MANIFEST_DIR")
-    ))
-    .unwrap();
-
-    {
-        let mut locked_planner = cloned_nav.planner.lock().unwrap();
-        *locked_planner = planner;
-    }
-
     std::thread::spawn(move || loop {
         if !*cloned_nav.is_run.lock().unwrap() {
             std::thread::sleep(std::time::Duration::from_millis(10));
@@ -59,7 +53,7 @@ fn main() {
                 Cell::Obstacle
             )
         };
-        const EXTEND_LENGTH: f64 = 0.05;
+        let GlobalPlannerConfig::Rrt(rrt_config) = &config.global_planner;
         let mut result = rrt::dual_rrt_connect(
             &[start[0], start[1]],
             &[goal[0], goal[1]],
@@ -68,12 +62,17 @@ fn main() {
                 let mut rng = rand::thread_rng();
                 vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
             },
-            EXTEND_LENGTH,
-            4000,
+            rrt_config.extend_length,
+            rrt_config.max_iterations,
         )
         .unwrap();
-        rrt::smooth_path(&mut result, is_free, EXTEND_LENGTH, 1000);
-        let result = linear_interpolate_path(result, EXTEND_LENGTH);
+        rrt::smooth_path(
+            &mut result,
+            is_free,
+            rrt_config.extend_length,
+            rrt_config.smooth_iterations,
+        );
+        let result = linear_interpolate_path(result, rrt_config.extend_length);
         let result = add_target_position_to_path(
             result,
             &Pose::new(Vector2::new(goal[0], goal[1]), goal[2]),
@@ -98,8 +97,13 @@ fn main() {
 
         let obstacle_distance_map = obstacle_distance_map(&map).unwrap();
 
-        let local_goal_distance_map =
-            local_goal_distance_map(&map, &result, [start[0], start[1]]).unwrap();
+        let local_goal_distance_map = local_goal_distance_map(
+            &map,
+            &result,
+            [start[0], start[1]],
+            &config.cost_maps.local_goal,
+        )
+        .unwrap();
 
         {
             let mut locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
@@ -123,12 +127,11 @@ fn main() {
         let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), start[2]);
         let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), goal[2]);
 
-        let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
+        let mut current_velocity = Velocity::default();
         let mut plan_map = map.clone();
 
         for i in 0..300 {
-            // let dynamic_map = new_dynamic_sample_map(i);
-            let dynamic_map = new_sample_map();
+            let dynamic_map = new_dynamic_sample_map(i);
             let path_distance_map =
                 openrr_nav::path_distance_map(&dynamic_map, &path_grid).unwrap();
 
@@ -142,6 +145,7 @@ fn main() {
                 &map,
                 &result,
                 [current_pose.translation.x, current_pose.translation.y],
+                &config.cost_maps.local_goal,
             )
             .unwrap();
 
@@ -164,13 +168,12 @@ fn main() {
                     &result,
                     [current_pose.translation.x, current_pose.translation.y],
                 );
-                let len = result.len();
                 let mut locked_angle_table = cloned_nav.angle_table.lock().unwrap();
                 locked_angle_table
                     .insert(ROTATION_COST_NAME.to_owned(), current_pose.rotation.angle());
-                const FORWARD_OFFSET: usize = 20;
                 if let Some((idx, _)) = nearest_path_point {
-                    let look_ahead_idx = (idx + FORWARD_OFFSET).min(len - 1);
+                    let look_ahead_idx =
+                        look_ahead_path_point(&result, idx, config.path_look_ahead_distance);
                     locked_angle_table.insert(
                         PATH_DIRECTION_COST_NAME.to_owned(),
                         result[look_ahead_idx][2],
@@ -178,20 +181,18 @@ fn main() {
                 }
             }
 
-            let (plan, candidates) = {
+            let candidates = {
                 let locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
                 let locked_angle_table = cloned_nav.angle_table.lock().unwrap();
                 let locked_planner = cloned_nav.planner.lock().unwrap();
-                (
-                    locked_planner.plan_local_path(
-                        &current_pose,
-                        &current_velocity,
-                        &locked_layered_grid_map,
-                        &locked_angle_table,
-                    ),
-                    locked_planner.predicted_plan_candidates(&current_pose, &current_velocity),
+                locked_planner.scored_plan_candidates(
+                    &current_pose,
+                    &current_velocity,
+                    &locked_layered_grid_map,
+                    &locked_angle_table,
                 )
             };
+            let plan = min_cost_plan(candidates.iter().cloned());
             {
                 let mut locked_robot_path = cloned_nav.robot_path.lock().unwrap();
                 locked_robot_path.set_local_path(RobotPath(plan.path.clone()));
@@ -202,6 +203,7 @@ fn main() {
                     );
                 }
             }
+            *cloned_nav.plan_candidates.lock().unwrap() = candidates;
 
             current_velocity = plan.velocity;
             current_pose = plan.path[0];
@@ -210,7 +212,7 @@ fn main() {
                 let mut locked_robot_pose = cloned_nav.robot_pose.lock().unwrap();
                 *locked_robot_pose = current_pose;
             }
-            std::thread::sleep(std::time::Duration::from_millis(50));
+            std::thread::sleep(config.controller_period());
 
             if let Some(grid) =
                 plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
@@ -220,12 +222,10 @@ fn main() {
                 println!("OUT OF MAP!");
                 return;
             }
-            const GOAL_THRESHOLD_DISTANCE: f64 = 0.1;
-            const GOAL_THRESHOLD_ANGLE_DIFFERENCE: f64 = 0.4;
             if (goal_pose.translation.vector - current_pose.translation.vector).norm()
-                < GOAL_THRESHOLD_DISTANCE
-                && (goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
-                    < GOAL_THRESHOLD_ANGLE_DIFFERENCE
+                < config.goal_tolerance.distance
+                && normalize_angle(goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
+                    < config.goal_tolerance.angle
             {
                 println!("GOAL! count = {i}");
                 break;
@@ -7,23 +7,29 @@ use openrr_nav_viewer::NavigationViz;
 #[derive(Debug, Parser)]
 pub struct Args {
     #[clap(
-        short = 'f',
-        long = "config-file",
+        short = 'n',
+        long = "navigation-config-file",
         default_value = concat!(
             env!("CARGO_MANIFEST_DIR"),
-            "/../openrr-nav/config/dwa_parameter_config.yaml"
+            "/../openrr-nav/config/navigation_config.yaml"
         ),
-        env = "PLANNER_CONFIG_PATH",
-        help = "planner config file path"
+        env = "NAVIGATION_CONFIG_PATH",
+        help = "navigation config file path, whose local_planner is used as the planner"
     )]
-    pub planner_config_path: String,
+    pub navigation_config_path: String,
+}
+
+impl Args {
+    pub fn navigation_config(&self) -> openrr_nav::Result<NavigationConfig> {
+        NavigationConfig::new_from_file(&self.navigation_config_path)
+    }
 }
 
 impl TryFrom<Args> for NavigationViz {
     type Error = openrr_nav::Error;
 
     fn try_from(value: Args) -> openrr_nav::Result<Self> {
-        NavigationViz::new(&value.planner_config_path)
+        NavigationViz::new_from_navigation_config(&value.navigation_config_path)
     }
 }
 
@@ -41,6 +47,25 @@ pub(crate) fn new_sample_map() -> GridMap<u8> {
     map
 }
 
+/// Sample map with an obstacle which moves back and forth across the map by a cell at each step
+pub(crate) fn new_dynamic_sample_map(step: usize) -> GridMap<u8> {
+    const SIZE: usize = 4;
+    let mut map = new_sample_map();
+    let range = map.height() - SIZE;
+    let phase = step % (2 * range);
+    let y0 = if phase < range {
+        phase
+    } else {
+        2 * range - phase
+    };
+    for x in 124..124 + SIZE {
+        for y in y0..y0 + SIZE {
+            map.set_obstacle(&Grid::new(x, y)).unwrap();
+        }
+    }
+    map
+}
+
 pub(crate) fn robot_path_from_vec_vec(path: Vec<Vec<f64>>) -> RobotPath {
     let mut robot_path_inner = vec![];
     for p in path {
@@ -452,11 +452,50 @@ fn ui_system(
             ui.separator();
             ui.label("");
 
-            if ui
-                .add_sized([200., 30.], egui::Button::new("Reload planner config"))
-                .clicked()
+            ui.horizontal(|ui| {
+                if ui
+                    .add_sized([200., 30.], egui::Button::new("Reload planner config"))
+                    .clicked()
+                {
+                    if let Err(e) = res_nav.reload_planner() {
+                        // keep the current planner
+                        eprintln!("failed to reload planner config: {e}");
+                    }
+                }
+                if ui
+                    .add_sized([200., 30.], egui::Button::new("Save planner config"))
+                    .clicked()
+                {
+                    if let Err(e) = res_nav.save_planner() {
+                        eprintln!("failed to save planner config: {e}");
+                    }
+                }
+            });
+            ui.label("");
+            ui.separator();
+            ui.label("");
+
+            ui.label("Cost breakdown of the selected plan");
+            let candidates = res_nav.plan_candidates.lock().unwrap();
+            if let Some(selected) = candidates
+                .iter()
+                .filter(|c| c.cost < f64::MAX)
+                .min_by(|a, b| a.cost.total_cmp(&b.cost))
             {
-                res_nav.reload_planner().unwrap();
+                let mut cost_breakdown = selected.cost_breakdown.iter().collect::<Vec<_>>();
+                cost_breakdown.sort_by(|a, b| a.0.cmp(b.0));
+                egui::Grid::new("cost_breakdown")
+                    .striped(true)
+                    .show(ui, |g_ui| {
+                        for (name, cost) in cost_breakdown {
+                            g_ui.label(name);
+                            g_ui.label(format!("{cost:.3}"));
+                            g_ui.end_row();
+                        }
+                        g_ui.label("total");
+                        g_ui.label(format!("{:.3}", selected.cost));
+                        g_ui.end_row();
+                    });
             }
         });
 }
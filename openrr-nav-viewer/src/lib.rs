@@ -51,14 +51,16 @@ impl pb::api_server::Api for NavigationViz {
         request: tonic::Request<pb::PathAndCandidates>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
         let pb::PathAndCandidates { path, candidates } = request.into_inner();
+        let candidates: Vec<openrr_nav::Plan> = candidates.into_iter().map(Into::into).collect();
         let mut robot_path = self.robot_path.lock().unwrap();
         robot_path.set_local_path(path.unwrap().into());
-        for (i, candidate) in candidates.into_iter().enumerate() {
+        for (i, candidate) in candidates.iter().enumerate() {
             robot_path.add_user_defined_path(
                 &format!("candidate_{i}"),
-                openrr_nav::RobotPath(candidate.path.into_iter().map(Into::into).collect()),
+                openrr_nav::RobotPath(candidate.path.clone()),
             );
         }
+        *self.plan_candidates.lock().unwrap() = candidates;
         Ok(tonic::Response::new(()))
     }
     async fn set_layered_grid_map(
@@ -99,7 +101,7 @@ impl pb::api_server::Api for NavigationViz {
             Ok(new) => *self.planner.lock().unwrap() = new,
             Err(e) => {
                 return Err(tonic::Status::invalid_argument(format!(
-                    "failed to parse config: {e}"
+                    "invalid config: {e}"
                 )));
             }
         }
@@ -148,6 +150,27 @@ impl pb::api_server::Api for NavigationViz {
             candidates: candidates.into_iter().map(Into::into).collect(),
         }))
     }
+    async fn scored_plan_candidates(
+        &self,
+        request: tonic::Request<pb::PlanRequest>,
+    ) -> Result<tonic::Response<pb::Candidates>, tonic::Status> {
+        let pb::PlanRequest {
+            current_pose,
+            current_velocity,
+        } = request.into_inner();
+        let layered_grid_map = self.layered_grid_map.lock().unwrap();
+        let angle_table = self.angle_table.lock().unwrap();
+        let planner = self.planner.lock().unwrap();
+        let candidates = planner.scored_plan_candidates(
+            &current_pose.unwrap().into(),
+            &current_velocity.unwrap().into(),
+            &layered_grid_map,
+            &angle_table,
+        );
+        Ok(tonic::Response::new(pb::Candidates {
+            candidates: candidates.into_iter().map(Into::into).collect(),
+        }))
+    }
 }
 
 impl From<openrr_nav::RobotPath> for pb::RobotPath {
@@ -178,6 +201,7 @@ impl From<pb::Velocity> for openrr_nav::Velocity {
     fn from(value: pb::Velocity) -> Self {
         Self {
             x: value.x,
+            y: value.vy,
             theta: value.theta,
         }
     }
@@ -187,6 +211,7 @@ impl From<openrr_nav::Velocity> for pb::Velocity {
         Self {
             x: value.x,
             theta: value.theta,
+            vy: value.y,
         }
     }
 }
@@ -194,25 +219,28 @@ impl From<openrr_nav::Velocity> for pb::Velocity {
 impl From<openrr_nav::Plan> for pb::Plan {
     fn from(val: openrr_nav::Plan) -> Self {
         Self {
-            velocity: Some(pb::Velocity {
-                x: val.velocity.x,
-                theta: val.velocity.theta,
-            }),
+            velocity: Some(val.velocity.into()),
             cost: val.cost,
             path: val.path.into_iter().map(Into::into).collect(),
+            cost_breakdown: val
+                .cost_breakdown
+                .into_iter()
+                .map(|(name, cost)| pb::NamedCost { name, cost })
+                .collect(),
         }
     }
 }
 impl From<pb::Plan> for openrr_nav::Plan {
     fn from(val: pb::Plan) -> Self {
-        let velocity = val.velocity.unwrap();
         Self {
-            velocity: openrr_nav::Velocity {
-                x: velocity.x,
-                theta: velocity.theta,
-            },
+            velocity: val.velocity.unwrap().into(),
             cost: val.cost,
             path: val.path.into_iter().map(Into::into).collect(),
+            cost_breakdown: val
+                .cost_breakdown
+                .into_iter()
+                .map(|named_cost| (named_cost.name, named_cost.cost))
+                .collect(),
         }
     }
 }
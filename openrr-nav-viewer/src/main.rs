@@ -7,16 +7,29 @@ struct Args {
         short = 'f',
         long = "config-file",
         env = "PLANNER_CONFIG_PATH",
-        help = "planner config file path"
+        help = "planner config file path",
+        required_unless_present = "navigation_config_path",
+        conflicts_with = "navigation_config_path"
     )]
-    planner_config_path: String,
+    planner_config_path: Option<String>,
+    #[clap(
+        short = 'n',
+        long = "navigation-config-file",
+        env = "NAVIGATION_CONFIG_PATH",
+        help = "navigation config file path, whose local_planner is used as the planner"
+    )]
+    navigation_config_path: Option<String>,
 }
 
 impl TryFrom<Args> for NavigationViz {
     type Error = openrr_nav::Error;
 
     fn try_from(value: Args) -> Result<Self, Self::Error> {
-        NavigationViz::new(&value.planner_config_path)
+        match (value.planner_config_path, value.navigation_config_path) {
+            (_, Some(path)) => NavigationViz::new_from_navigation_config(&path),
+            (Some(path), None) => NavigationViz::new(&path),
+            (None, None) => unreachable!("clap requires one of the config files"),
+        }
     }
 }
 
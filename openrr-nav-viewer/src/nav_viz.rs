@@ -3,42 +3,93 @@ use grid_map::*;
 use openrr_nav::*;
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::{Arc, Mutex},
 };
 
+/// Config file which the planner is loaded from, reloaded from, and saved to
+#[derive(Debug, Clone)]
+pub enum PlannerConfigSource {
+    /// DWA planner config file
+    Planner(PathBuf),
+    /// `local_planner` section of the navigation config file
+    Navigation(PathBuf),
+}
+
+impl PlannerConfigSource {
+    fn load(&self) -> openrr_nav::Result<DwaPlanner> {
+        match self {
+            Self::Planner(path) => DwaPlanner::new_from_config(path),
+            Self::Navigation(path) => Ok(NavigationConfig::new_from_file(path)?.local_planner),
+        }
+    }
+
+    fn save(&self, planner: &DwaPlanner) -> openrr_nav::Result<()> {
+        match self {
+            Self::Planner(path) => planner.save_config(path),
+            Self::Navigation(path) => NavigationConfig::save_local_planner(path, planner),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Resource)]
 pub struct NavigationViz {
     pub layered_grid_map: Arc<Mutex<LayeredGridMap<u8>>>,
     pub angle_table: Arc<Mutex<HashMap<String, f64>>>,
     pub robot_path: Arc<Mutex<NavigationRobotPath>>,
+    pub plan_candidates: Arc<Mutex<Vec<Plan>>>,
     pub robot_pose: Arc<Mutex<Pose>>,
     pub is_run: Arc<Mutex<bool>>,
     pub start_position: Arc<Mutex<Pose>>,
     pub goal_position: Arc<Mutex<Pose>>,
     pub planner: Arc<Mutex<DwaPlanner>>,
-    planner_config_path: String,
+    planner_config_source: PlannerConfigSource,
 }
 
 impl NavigationViz {
     pub fn new(planner_config_path: &str) -> openrr_nav::Result<Self> {
-        let planner = DwaPlanner::new_from_config(planner_config_path)?;
+        Self::new_from_source(PlannerConfigSource::Planner(planner_config_path.into()))
+    }
+
+    /// Use the `local_planner` section of the navigation config as the planner
+    pub fn new_from_navigation_config(navigation_config_path: &str) -> openrr_nav::Result<Self> {
+        Self::new_from_source(PlannerConfigSource::Navigation(
+            navigation_config_path.into(),
+        ))
+    }
+
+    pub fn new_from_source(planner_config_source: PlannerConfigSource) -> openrr_nav::Result<Self> {
+        let planner = planner_config_source.load()?;
         Ok(Self {
             layered_grid_map: Default::default(),
             angle_table: Default::default(),
             robot_path: Default::default(),
+            plan_candidates: Default::default(),
             robot_pose: Default::default(),
             is_run: Arc::new(Mutex::new(true)),
             start_position: Arc::new(Mutex::new(Pose::new(Vector2::new(-1.6, -1.8), 0.0))),
             goal_position: Arc::new(Mutex::new(Pose::new(Vector2::new(5.0, 1.0), 0.0))),
             planner: Arc::new(Mutex::new(planner)),
-            planner_config_path: planner_config_path.to_string(),
+            planner_config_source,
         })
     }
 
+    pub fn planner_config_source(&self) -> &PlannerConfigSource {
+        &self.planner_config_source
+    }
+
+    /// Load the planner again from the file it was loaded from
     pub fn reload_planner(&self) -> openrr_nav::Result<()> {
-        let planner = DwaPlanner::new_from_config(&self.planner_config_path)?;
+        let planner = self.planner_config_source.load()?;
         let mut locked_planner = self.planner.lock().unwrap();
         *locked_planner = planner;
         Ok(())
     }
+
+    /// Write the current planner config, including the tuned weights, to the file it was loaded
+    /// from
+    pub fn save_planner(&self) -> openrr_nav::Result<()> {
+        self.planner_config_source
+            .save(&self.planner.lock().unwrap())
+    }
 }
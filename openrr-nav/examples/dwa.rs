@@ -75,14 +75,24 @@ fn main() {
 
     let planner = DwaPlanner::new(
         Limits {
-            max_velocity: Velocity { x: 0.5, theta: 2.0 },
-            max_accel: Acceleration { x: 2.0, theta: 5.0 },
+            max_velocity: Velocity {
+                x: 0.5,
+                y: 0.0,
+                theta: 2.0,
+            },
+            max_accel: Acceleration {
+                x: 2.0,
+                y: 0.0,
+                theta: 5.0,
+            },
             min_velocity: Velocity {
                 x: 0.0,
+                y: 0.0,
                 theta: -2.0,
             },
             min_accel: Acceleration {
                 x: -2.0,
+                y: 0.0,
                 theta: -5.0,
             },
         },
@@ -94,7 +104,7 @@ fn main() {
 
     let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), 0.0);
     let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), 0.0);
-    let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
+    let mut current_velocity = Velocity::default();
     let mut plan_map = map.clone();
     for i in 0..100 {
         let plan = planner.plan_local_path(&current_pose, &current_velocity, &layered, &angles);
@@ -0,0 +1,102 @@
+//! Benchmark of the DWA planner
+//!
+//! Compare the sequential and the parallel scoring:
+//!
+//! ```sh
+//! cargo run --release -p openrr-nav --example dwa_bench
+//! cargo run --release -p openrr-nav --example dwa_bench --features parallel
+//! ```
+use grid_map::*;
+use openrr_nav::*;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const WARM_UP_ITERATIONS: usize = 10;
+const ITERATIONS: usize = 100;
+
+fn new_sample_map() -> GridMap<u8> {
+    let mut map =
+        grid_map::GridMap::<u8>::new(Position::new(-1.05, -1.05), Position::new(3.05, 1.05), 0.05);
+    for i in 10..50 {
+        map.set_obstacle(&Grid::new(i + 10, 5)).unwrap();
+        map.set_obstacle(&Grid::new(i + 10, 6)).unwrap();
+        for j in 20..30 {
+            map.set_obstacle(&Grid::new(i, j)).unwrap();
+        }
+    }
+    map
+}
+
+fn bench(name: &str, mut f: impl FnMut() -> Plan) {
+    for _ in 0..WARM_UP_ITERATIONS {
+        f();
+    }
+    let mut times = Vec::with_capacity(ITERATIONS);
+    let mut plan = Plan::default();
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        plan = f();
+        times.push(start.elapsed());
+    }
+    times.sort();
+    let mean = times.iter().sum::<Duration>() / ITERATIONS as u32;
+    println!(
+        "{name:<24} time: [{:?} {:?} {:?}] (min mean max), selected: {:?}",
+        times[0],
+        mean,
+        times[ITERATIONS - 1],
+        plan.velocity
+    );
+}
+
+fn main() {
+    let map = new_sample_map();
+    let path_grid = (0..map.width())
+        .map(|i| Grid::new(i, 2))
+        .collect::<Vec<_>>();
+    let goal_grid = Grid::new(map.width() - 1, 2);
+    let mut maps = HashMap::new();
+    maps.insert(
+        "path".to_owned(),
+        path_distance_map(&map, &path_grid).unwrap(),
+    );
+    maps.insert(
+        "goal".to_owned(),
+        goal_distance_map(&map, &goal_grid).unwrap(),
+    );
+    maps.insert("obstacle".to_owned(), obstacle_distance_map(&map).unwrap());
+    let mut weights = HashMap::new();
+    weights.insert("path".to_owned(), 0.8);
+    weights.insert("goal".to_owned(), 0.9);
+    weights.insert("obstacle".to_owned(), 0.3);
+    // seven layers in total
+    for i in 0..4 {
+        let name = format!("extra{i}");
+        maps.insert(name.clone(), maps["obstacle"].clone());
+        weights.insert(name, 0.1);
+    }
+    let layered = LayeredGridMap::new(maps);
+    let angles = HashMap::new();
+
+    for num_vel_sample in [5, 10, 20] {
+        let planner = DwaPlanner::new(
+            Limits {
+                max_velocity: [0.5, 2.0].into(),
+                max_accel: [2.0, 5.0].into(),
+                min_velocity: [0.0, -2.0].into(),
+                min_accel: [-2.0, -5.0].into(),
+            },
+            weights.clone(),
+            0.1,
+            1.0,
+            num_vel_sample,
+        );
+        let current_pose = Pose::new(Vector2::new(-0.8, -0.9), 0.0);
+        let current_velocity = [0.2, 0.0].into();
+        bench(&format!("num_vel_sample = {num_vel_sample}"), || {
+            planner.plan_local_path(&current_pose, &current_velocity, &layered, &angles)
+        });
+    }
+}
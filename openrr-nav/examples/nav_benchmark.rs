@@ -0,0 +1,29 @@
+//! Navigation benchmark over the scenario file
+//!
+//! ```sh
+//! cargo run --release -p openrr-nav --example nav_benchmark -- config/benchmark/scenarios.yaml
+//! cargo run --release -p openrr-nav --example nav_benchmark -- config/benchmark/scenarios.yaml csv
+//! ```
+//!
+//! The exit code is non-zero if any scenario fails.
+use openrr_nav::*;
+use std::process::ExitCode;
+
+fn main() -> Result<ExitCode> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| "config/benchmark/scenarios.yaml".to_owned());
+    let format = args.next().unwrap_or_else(|| "json".to_owned());
+    let report = BenchmarkScenarios::new_from_file(path)?.run()?;
+    match format.as_str() {
+        "json" => println!("{}", report.to_json()?),
+        "csv" => print!("{}", report.to_csv()),
+        _ => return Err(Error::Other(format!("unknown format `{format}`"))),
+    }
+    Ok(if report.success_rate < 1.0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,720 @@
+use std::collections::{HashMap, HashSet};
+
+use grid_map::{Cell, GridMap};
+use nalgebra as na;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::{normalize_angle, sample_normal, to_arci_pose},
+    Error, LaserScan, LikelihoodField, Pose, Result,
+};
+
+/// Odometry motion model of the particles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OdometryModel {
+    #[default]
+    Differential,
+    Omni,
+}
+
+/// Parameters of [`Amcl`], which follow the ones of ROS amcl
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AmclConfig {
+    pub min_particles: usize,
+    pub max_particles: usize,
+    /// maximum error between the true and the estimated distributions of KLD sampling
+    pub kld_err: f64,
+    /// upper standard normal quantile of KLD sampling
+    pub kld_z: f64,
+    /// size of the histogram bins of KLD sampling in x and y [m]
+    pub bin_size_xy: f64,
+    /// size of the histogram bins of KLD sampling in theta [rad]
+    pub bin_size_angle: f64,
+    pub odometry_model: OdometryModel,
+    /// noise of the odometry model, same as `odom_alpha1` - `odom_alpha5` of ROS amcl
+    ///
+    /// `odometry_alpha[4]` is used only by the omni model.
+    pub odometry_alpha: [f64; 5],
+    /// pose of the laser on the robot [x, y, theta]
+    pub laser_pose: [f64; 3],
+    /// maximum number of the beams used in the update
+    pub max_beams: usize,
+    pub z_hit: f64,
+    pub z_rand: f64,
+    /// standard deviation of the measurement noise [m]
+    pub sigma_hit: f64,
+    /// maximum distance of the likelihood field [m]
+    pub likelihood_max_distance: f64,
+    /// translation required before the next filter update [m]
+    pub update_min_distance: f64,
+    /// rotation required before the next filter update [rad]
+    pub update_min_angle: f64,
+    /// seed of the random number generator, which is random if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for AmclConfig {
+    fn default() -> Self {
+        Self {
+            min_particles: 100,
+            max_particles: 5000,
+            kld_err: 0.01,
+            kld_z: 0.99,
+            bin_size_xy: 0.5,
+            bin_size_angle: 10.0_f64.to_radians(),
+            odometry_model: OdometryModel::default(),
+            odometry_alpha: [0.2; 5],
+            laser_pose: [0.0; 3],
+            max_beams: 30,
+            z_hit: 0.95,
+            z_rand: 0.05,
+            sigma_hit: 0.2,
+            likelihood_max_distance: 2.0,
+            update_min_distance: 0.2,
+            update_min_angle: std::f64::consts::FRAC_PI_6,
+            seed: None,
+        }
+    }
+}
+
+impl AmclConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.min_particles == 0 {
+            return Err(Error::invalid_config("min_particles", "must be positive"));
+        }
+        if self.max_particles < self.min_particles {
+            return Err(Error::invalid_config(
+                "max_particles",
+                format!("must be >= min_particles ({})", self.min_particles),
+            ));
+        }
+        if self.max_beams == 0 {
+            return Err(Error::invalid_config("max_beams", "must be positive"));
+        }
+        for (field, value) in [
+            ("kld_err", self.kld_err),
+            ("kld_z", self.kld_z),
+            ("bin_size_xy", self.bin_size_xy),
+            ("bin_size_angle", self.bin_size_angle),
+            ("sigma_hit", self.sigma_hit),
+            ("likelihood_max_distance", self.likelihood_max_distance),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be positive, but {value}"),
+                ));
+            }
+        }
+        for (field, value) in [
+            ("z_hit", self.z_hit),
+            ("z_rand", self.z_rand),
+            ("update_min_distance", self.update_min_distance),
+            ("update_min_angle", self.update_min_angle),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be non-negative, but {value}"),
+                ));
+            }
+        }
+        if self.z_hit + self.z_rand <= 0.0 {
+            return Err(Error::invalid_config(
+                "z_hit",
+                "z_hit + z_rand must be positive",
+            ));
+        }
+        for (i, alpha) in self.odometry_alpha.iter().enumerate() {
+            if !(alpha.is_finite() && *alpha >= 0.0) {
+                return Err(Error::invalid_config(
+                    format!("odometry_alpha[{i}]"),
+                    format!("must be non-negative, but {alpha}"),
+                ));
+            }
+        }
+        if !self.laser_pose.iter().all(|v| v.is_finite()) {
+            return Err(Error::invalid_config("laser_pose", "must be finite"));
+        }
+        Ok(())
+    }
+}
+
+/// Hypothesis of the robot pose
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub pose: Pose,
+    pub weight: f64,
+}
+
+/// Adaptive Monte Carlo localization on a `GridMap`
+///
+/// The particles are moved by the odometry and weighted by the likelihood field of the laser
+/// scans, then resampled with KLD sampling. The estimate is the weighted mean of the cluster
+/// which has the most weight.
+#[derive(Debug)]
+pub struct Amcl {
+    config: AmclConfig,
+    field: LikelihoodField,
+    /// centers of the cells which the robot can be on
+    free_positions: Vec<[f64; 2]>,
+    resolution: f64,
+    particles: Vec<Particle>,
+    rng: StdRng,
+    laser_pose: Pose,
+    /// latest odometry
+    odometry: Option<Pose>,
+    /// odometry at the last filter update
+    filter_odometry: Option<Pose>,
+    /// estimate at the last filter update
+    filter_estimate: Pose,
+    /// update the filter at the next scan even if the robot does not move
+    force_update: bool,
+}
+
+impl Amcl {
+    pub fn new(map: &GridMap<u8>, config: AmclConfig) -> Result<Self> {
+        config.validate()?;
+        let resolution = map.resolution();
+        let free_positions = map
+            .cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !matches!(cell, Cell::Obstacle | Cell::Unknown))
+            .map(|(index, _)| {
+                [
+                    map.min_point().x + ((index % map.width()) as f64 + 0.5) * resolution,
+                    map.min_point().y + ((index / map.width()) as f64 + 0.5) * resolution,
+                ]
+            })
+            .collect::<Vec<_>>();
+        if free_positions.is_empty() {
+            return Err(Error::Other("map has no free cells".to_owned()));
+        }
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let [x, y, theta] = config.laser_pose;
+        Ok(Self {
+            field: LikelihoodField::new(map, config.likelihood_max_distance),
+            free_positions,
+            resolution,
+            particles: vec![],
+            rng,
+            laser_pose: Pose::new(na::Vector2::new(x, y), theta),
+            odometry: None,
+            filter_odometry: None,
+            filter_estimate: Pose::identity(),
+            force_update: false,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &AmclConfig {
+        &self.config
+    }
+
+    pub fn likelihood_field(&self) -> &LikelihoodField {
+        &self.field
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Whether the particles have been initialized
+    pub fn is_initialized(&self) -> bool {
+        !self.particles.is_empty()
+    }
+
+    /// Initialize the particles around the pose with the standard deviation of [x, y, theta]
+    pub fn set_initial_pose(&mut self, pose: &Pose, std_dev: [f64; 3]) {
+        let (x, y, theta) = (
+            pose.translation.x,
+            pose.translation.y,
+            pose.rotation.angle(),
+        );
+        let weight = 1.0 / self.config.max_particles as f64;
+        let rng = &mut self.rng;
+        self.particles = (0..self.config.max_particles)
+            .map(|_| Particle {
+                pose: Pose::new(
+                    na::Vector2::new(
+                        x + sample_normal(rng, std_dev[0]),
+                        y + sample_normal(rng, std_dev[1]),
+                    ),
+                    theta + sample_normal(rng, std_dev[2]),
+                ),
+                weight,
+            })
+            .collect();
+        self.reset_filter(*pose);
+    }
+
+    /// Initialize the particles uniformly over the free cells of the map
+    pub fn global_localization(&mut self) {
+        let weight = 1.0 / self.config.max_particles as f64;
+        let half = self.resolution / 2.0;
+        let rng = &mut self.rng;
+        let free_positions = &self.free_positions;
+        self.particles = (0..self.config.max_particles)
+            .map(|_| {
+                let [x, y] = free_positions[rng.gen_range(0..free_positions.len())];
+                Particle {
+                    pose: Pose::new(
+                        na::Vector2::new(
+                            x + rng.gen_range(-half..half),
+                            y + rng.gen_range(-half..half),
+                        ),
+                        rng.gen_range(-std::f64::consts::PI..std::f64::consts::PI),
+                    ),
+                    weight,
+                }
+            })
+            .collect();
+        let estimate = self.mean_pose();
+        self.reset_filter(estimate);
+    }
+
+    fn reset_filter(&mut self, estimate: Pose) {
+        self.filter_odometry = self.odometry;
+        self.filter_estimate = estimate;
+        self.force_update = true;
+    }
+
+    /// Update the filter with the odometry pose and the laser scan
+    ///
+    /// The filter is updated only if the robot moved more than the thresholds since the last
+    /// update, or just after the initialization. Returns whether the filter is updated.
+    pub fn update(&mut self, odometry: &Pose, scan: &LaserScan) -> bool {
+        self.odometry = Some(*odometry);
+        if self.particles.is_empty() {
+            return false;
+        }
+        let last = *self.filter_odometry.get_or_insert(*odometry);
+        let delta = last.inverse() * odometry;
+        let moved = delta.translation.vector.norm() >= self.config.update_min_distance
+            || delta.rotation.angle().abs() >= self.config.update_min_angle;
+        if !moved && !self.force_update {
+            return false;
+        }
+        self.apply_motion(&last, odometry);
+        self.apply_scan(scan);
+        self.resample();
+        self.filter_odometry = Some(*odometry);
+        self.filter_estimate = self.mean_pose();
+        self.force_update = false;
+        true
+    }
+
+    /// Estimated pose of the robot in the map frame
+    ///
+    /// The motion after the last filter update is added by the odometry.
+    pub fn estimate(&self) -> Pose {
+        match (self.filter_odometry, self.odometry) {
+            (Some(last), Some(odometry)) => self.filter_estimate * last.inverse() * odometry,
+            _ => self.filter_estimate,
+        }
+    }
+
+    /// Covariance of the particles in [x, y, theta]
+    pub fn covariance(&self) -> na::Matrix3<f64> {
+        let mean = weighted_mean(self.particles.iter().copied());
+        let mean_angle = mean.rotation.angle();
+        self.particles
+            .iter()
+            .map(|p| {
+                let d = na::Vector3::new(
+                    p.pose.translation.x - mean.translation.x,
+                    p.pose.translation.y - mean.translation.y,
+                    normalize_angle(p.pose.rotation.angle() - mean_angle),
+                );
+                d * d.transpose() * p.weight
+            })
+            .sum()
+    }
+
+    /// Weighted mean of the particles around the heaviest bin of KLD sampling
+    fn mean_pose(&self) -> Pose {
+        let mut bin_weights = HashMap::new();
+        for p in &self.particles {
+            *bin_weights.entry(self.bin(&p.pose)).or_insert(0.0) += p.weight;
+        }
+        let Some(best) = bin_weights
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(bin, _)| bin)
+        else {
+            return Pose::identity();
+        };
+        let center = weighted_mean(
+            self.particles
+                .iter()
+                .copied()
+                .filter(|p| self.bin(&p.pose) == best),
+        );
+        // the cluster may be split by the bin boundaries
+        weighted_mean(self.particles.iter().copied().filter(|p| {
+            (p.pose.translation.vector - center.translation.vector).norm() < self.config.bin_size_xy
+                && normalize_angle(p.pose.rotation.angle() - center.rotation.angle()).abs()
+                    < self.config.bin_size_angle
+        }))
+    }
+
+    /// Sample the particles moved by the odometry from `old` to `new`
+    fn apply_motion(&mut self, old: &Pose, new: &Pose) {
+        let dx = new.translation.x - old.translation.x;
+        let dy = new.translation.y - old.translation.y;
+        let old_yaw = old.rotation.angle();
+        let delta_yaw = normalize_angle(new.rotation.angle() - old_yaw);
+        let trans = dx.hypot(dy);
+        let [a1, a2, a3, a4, a5] = self.config.odometry_alpha;
+        let rng = &mut self.rng;
+        match self.config.odometry_model {
+            OdometryModel::Differential => {
+                // avoid the noisy direction of the tiny translation
+                let rot1 = if trans < 0.01 {
+                    0.0
+                } else {
+                    normalize_angle(dy.atan2(dx) - old_yaw)
+                };
+                let rot2 = normalize_angle(delta_yaw - rot1);
+                // the backward motion is as noisy as the forward one
+                let rot1_noise = rot1
+                    .abs()
+                    .min(normalize_angle(rot1 - std::f64::consts::PI).abs());
+                let rot2_noise = rot2
+                    .abs()
+                    .min(normalize_angle(rot2 - std::f64::consts::PI).abs());
+                let rot1_std = (a1 * rot1_noise.powi(2) + a2 * trans.powi(2)).sqrt();
+                let trans_std =
+                    (a3 * trans.powi(2) + a4 * rot1_noise.powi(2) + a4 * rot2_noise.powi(2)).sqrt();
+                let rot2_std = (a1 * rot2_noise.powi(2) + a2 * trans.powi(2)).sqrt();
+                for p in &mut self.particles {
+                    let rot1_hat = rot1 - sample_normal(rng, rot1_std);
+                    let trans_hat = trans - sample_normal(rng, trans_std);
+                    let rot2_hat = rot2 - sample_normal(rng, rot2_std);
+                    let yaw = p.pose.rotation.angle();
+                    p.pose = Pose::new(
+                        na::Vector2::new(
+                            p.pose.translation.x + trans_hat * (yaw + rot1_hat).cos(),
+                            p.pose.translation.y + trans_hat * (yaw + rot1_hat).sin(),
+                        ),
+                        normalize_angle(yaw + rot1_hat + rot2_hat),
+                    );
+                }
+            }
+            OdometryModel::Omni => {
+                let trans_std = (a3 * trans.powi(2) + a1 * delta_yaw.powi(2)).sqrt();
+                let rot_std = (a4 * delta_yaw.powi(2) + a2 * trans.powi(2)).sqrt();
+                let strafe_std = (a1 * delta_yaw.powi(2) + a5 * trans.powi(2)).sqrt();
+                let direction = dy.atan2(dx) - old_yaw;
+                for p in &mut self.particles {
+                    let yaw = p.pose.rotation.angle();
+                    let bearing = direction + yaw;
+                    let trans_hat = trans + sample_normal(rng, trans_std);
+                    let rot_hat = delta_yaw + sample_normal(rng, rot_std);
+                    let strafe_hat = sample_normal(rng, strafe_std);
+                    p.pose = Pose::new(
+                        na::Vector2::new(
+                            p.pose.translation.x
+                                + trans_hat * bearing.cos()
+                                + strafe_hat * bearing.sin(),
+                            p.pose.translation.y + trans_hat * bearing.sin()
+                                - strafe_hat * bearing.cos(),
+                        ),
+                        normalize_angle(yaw + rot_hat),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Weight the particles by the likelihood field model
+    fn apply_scan(&mut self, scan: &LaserScan) {
+        let step = (scan.ranges.len() / self.config.max_beams).max(1);
+        let points = scan
+            .ranges
+            .iter()
+            .enumerate()
+            .step_by(step)
+            .filter(|(_, range)| scan.is_valid_range(**range))
+            .map(|(i, range)| {
+                let angle = scan.angle(i);
+                self.laser_pose * na::Point2::new(range * angle.cos(), range * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        if points.is_empty() {
+            return;
+        }
+        let z_rand = if scan.range_max.is_finite() && scan.range_max > 0.0 {
+            self.config.z_rand / scan.range_max
+        } else {
+            0.0
+        };
+        let denominator = 2.0 * self.config.sigma_hit.powi(2);
+        let mut sum = 0.0;
+        for p in &mut self.particles {
+            // cube of the probabilities as ROS amcl, which is less peaky than the product
+            let likelihood = 1.0
+                + points
+                    .iter()
+                    .map(|point| {
+                        let point = p.pose * point;
+                        let d = self.field.distance(point.x, point.y);
+                        (self.config.z_hit * (-d * d / denominator).exp() + z_rand).powi(3)
+                    })
+                    .sum::<f64>();
+            p.weight *= likelihood;
+            sum += p.weight;
+        }
+        for p in &mut self.particles {
+            p.weight /= sum;
+        }
+    }
+
+    /// Resample the particles with KLD sampling
+    fn resample(&mut self) {
+        let mut cumulative = Vec::with_capacity(self.particles.len());
+        let mut sum = 0.0;
+        for p in &self.particles {
+            sum += p.weight;
+            cumulative.push(sum);
+        }
+        let mut bins = HashSet::new();
+        let mut resampled = vec![];
+        let mut limit = self.config.max_particles;
+        while resampled.len() < limit {
+            let r = self.rng.gen::<f64>() * sum;
+            let index = cumulative
+                .partition_point(|c| *c < r)
+                .min(self.particles.len() - 1);
+            let pose = self.particles[index].pose;
+            if bins.insert(self.bin(&pose)) {
+                limit = self.kld_limit(bins.len());
+            }
+            resampled.push(Particle { pose, weight: 1.0 });
+        }
+        let weight = 1.0 / resampled.len() as f64;
+        for p in &mut resampled {
+            p.weight = weight;
+        }
+        self.particles = resampled;
+    }
+
+    fn bin(&self, pose: &Pose) -> [i64; 3] {
+        [
+            (pose.translation.x / self.config.bin_size_xy).floor() as i64,
+            (pose.translation.y / self.config.bin_size_xy).floor() as i64,
+            (normalize_angle(pose.rotation.angle()) / self.config.bin_size_angle).floor() as i64,
+        ]
+    }
+
+    /// Number of the particles required for the number of the occupied bins
+    fn kld_limit(&self, bins: usize) -> usize {
+        let n = if bins <= 1 {
+            0
+        } else {
+            let k = (bins - 1) as f64;
+            let b = 2.0 / (9.0 * k);
+            let x = 1.0 - b + b.sqrt() * self.config.kld_z;
+            (k / (2.0 * self.config.kld_err) * x.powi(3)).ceil() as usize
+        };
+        n.clamp(self.config.min_particles, self.config.max_particles)
+    }
+}
+
+fn weighted_mean(particles: impl Iterator<Item = Particle>) -> Pose {
+    let mut x = 0.0;
+    let mut y = 0.0;
+    let mut cos = 0.0;
+    let mut sin = 0.0;
+    let mut sum = 0.0;
+    for p in particles {
+        let angle = p.pose.rotation.angle();
+        x += p.weight * p.pose.translation.x;
+        y += p.weight * p.pose.translation.y;
+        cos += p.weight * angle.cos();
+        sin += p.weight * angle.sin();
+        sum += p.weight;
+    }
+    if sum <= 0.0 {
+        return Pose::identity();
+    }
+    Pose::new(na::Vector2::new(x / sum, y / sum), sin.atan2(cos))
+}
+
+impl arci::Localization for Amcl {
+    /// The pose in the map frame, `frame_id` is ignored.
+    fn current_pose(
+        &self,
+        _frame_id: &str,
+    ) -> std::result::Result<arci::Isometry2<f64>, arci::Error> {
+        if !self.is_initialized() {
+            return Err(arci::Error::Uninitialized {
+                message: "AMCL needs the initial pose or the global localization".to_owned(),
+            });
+        }
+        Ok(to_arci_pose(&self.estimate()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arci::Localization;
+    use grid_map::{Grid, Position};
+
+    /// 4m x 3m room with a block in a corner
+    fn new_room() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(4.0, 3.0), 0.05);
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 0)).unwrap();
+            map.set_obstacle(&Grid::new(x, map.height() - 1)).unwrap();
+        }
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(0, y)).unwrap();
+            map.set_obstacle(&Grid::new(map.width() - 1, y)).unwrap();
+        }
+        for x in 55..70 {
+            for y in 35..60 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        for x in 10..20 {
+            for y in 8..12 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        map
+    }
+
+    /// Cast the rays of the scan from the pose
+    fn scan_at(map: &GridMap<u8>, pose: &Pose) -> LaserScan {
+        let mut scan = LaserScan {
+            angle_min: -std::f64::consts::PI,
+            angle_increment: std::f64::consts::PI / 90.0,
+            range_min: 0.05,
+            range_max: 8.0,
+            ranges: vec![],
+        };
+        for i in 0..180 {
+            let angle = pose.rotation.angle() + scan.angle(i);
+            let mut range = f64::INFINITY;
+            let mut r = 0.0;
+            while r < scan.range_max {
+                let x = pose.translation.x + r * angle.cos();
+                let y = pose.translation.y + r * angle.sin();
+                match map.to_grid(x, y).and_then(|g| map.cell(&g)) {
+                    Some(Cell::Obstacle) | None => {
+                        range = r;
+                        break;
+                    }
+                    _ => r += 0.01,
+                }
+            }
+            scan.ranges.push(range);
+        }
+        scan
+    }
+
+    fn distance(a: &Pose, b: &Pose) -> f64 {
+        (a.translation.vector - b.translation.vector).norm()
+    }
+
+    #[test]
+    fn amcl_tracking_test() {
+        let map = new_room();
+        let config = AmclConfig {
+            max_particles: 500,
+            odometry_alpha: [0.05; 5],
+            seed: Some(0),
+            ..Default::default()
+        };
+        let mut amcl = Amcl::new(&map, config).unwrap();
+        let mut truth = Pose::new(na::Vector2::new(0.8, 1.2), 0.0);
+        // the odometry frame is different from the map frame
+        let odometry_origin = Pose::new(na::Vector2::new(-0.5, 0.3), 0.4);
+        assert!(amcl.current_pose("map").is_err());
+        assert!(!amcl.update(&(odometry_origin * truth), &LaserScan::default()));
+        amcl.set_initial_pose(&Pose::new(na::Vector2::new(0.9, 1.1), 0.1), [0.1, 0.1, 0.1]);
+        let step = Pose::new(na::Vector2::new(0.05, 0.0), 0.005);
+        let mut updated = 0;
+        for _ in 0..40 {
+            let odometry = odometry_origin * truth;
+            if amcl.update(&odometry, &scan_at(&map, &truth)) {
+                updated += 1;
+            }
+            truth *= step;
+        }
+        assert!(updated > 5);
+        assert!(updated < 40);
+        truth *= step.inverse();
+        let estimate = amcl.estimate();
+        assert!(distance(&estimate, &truth) < 0.1, "{estimate} {truth}");
+        assert!(normalize_angle(estimate.rotation.angle() - truth.rotation.angle()).abs() < 0.1);
+        let covariance = amcl.covariance();
+        assert!(covariance[(0, 0)] < 0.05);
+        assert!(covariance[(2, 2)] < 0.05);
+
+        let pose = amcl.current_pose("map").unwrap();
+        assert!((pose.translation.x - estimate.translation.x).abs() < 1e-9);
+    }
+
+    #[test]
+    fn amcl_global_localization_test() {
+        let map = new_room();
+        for odometry_model in [OdometryModel::Differential, OdometryModel::Omni] {
+            let config = AmclConfig {
+                max_particles: 3000,
+                odometry_model,
+                update_min_distance: 0.0,
+                seed: Some(1),
+                ..Default::default()
+            };
+            let mut amcl = Amcl::new(&map, config).unwrap();
+            amcl.global_localization();
+            assert_eq!(amcl.particles().len(), 3000);
+
+            let mut truth = Pose::new(na::Vector2::new(1.2, 1.5), -0.5);
+            let step = Pose::new(na::Vector2::new(0.05, 0.0), 0.08);
+            for _ in 0..30 {
+                amcl.update(&truth, &scan_at(&map, &truth));
+                truth *= step;
+            }
+            truth *= step.inverse();
+            let estimate = amcl.estimate();
+            assert!(distance(&estimate, &truth) < 0.2, "{estimate} {truth}");
+        }
+    }
+
+    #[test]
+    fn amcl_config_test() {
+        let map = new_room();
+        assert!(AmclConfig::default().validate().is_ok());
+        let config = AmclConfig {
+            max_particles: 10,
+            min_particles: 20,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Amcl::new(&map, config),
+            Err(crate::Error::InvalidConfig { field, .. }) if field == "max_particles"
+        ));
+        let config = AmclConfig {
+            sigma_hit: 0.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config: AmclConfig =
+            serde_yaml::from_str("odometry_model: omni\nmax_particles: 1000").unwrap();
+        assert_eq!(config.odometry_model, OdometryModel::Omni);
+        assert_eq!(config.max_particles, 1000);
+        assert_eq!(config.min_particles, 100);
+    }
+}
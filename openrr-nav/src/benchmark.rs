@@ -0,0 +1,463 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use arci::MoveBase;
+use grid_map::{Cell, Grid, GridMap, LayeredGridMap};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    goal_distance_map, local_goal_distance_map, obstacle_distance_map, path_distance_map,
+    utils::{look_ahead_path_point, nearest_path_point, normalize_angle, shortest_grid_path},
+    Error, GoalTolerance, LikelihoodField, NavigationConfig, Pose, Result, RobotSimulator,
+    RobotSimulatorConfig, Velocity,
+};
+
+/// Obstacles farther than it are not distinguished in the clearance [m]
+const MAX_CLEARANCE: f64 = 5.0;
+
+/// Navigation task of the benchmark
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    /// map yaml in the format of ROS `map_server`
+    pub map: PathBuf,
+    /// `[x, y, theta]`
+    pub start: [f64; 3],
+    /// `[x, y, theta]`
+    pub goal: [f64; 3],
+    /// config file of the navigation, whose `map` is not used.
+    /// The default config is used if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub navigation_config: Option<PathBuf>,
+    /// the navigation fails if the robot doesn't reach the goal within it [s]
+    #[serde(default = "default_timeout")]
+    pub timeout: f64,
+    /// the global path keeps this distance from the obstacles in addition to the robot radius [m]
+    #[serde(default = "default_path_margin")]
+    pub path_margin: f64,
+    #[serde(default)]
+    pub simulator: RobotSimulatorConfig,
+}
+
+fn default_timeout() -> f64 {
+    60.0
+}
+
+fn default_path_margin() -> f64 {
+    0.2
+}
+
+/// Scenario file of the benchmark
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkScenarios {
+    pub scenarios: Vec<Scenario>,
+}
+
+impl BenchmarkScenarios {
+    /// Load the scenario file
+    ///
+    /// The relative paths of the maps and the navigation configs are resolved from the directory
+    /// of the file.
+    pub fn new_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut scenarios = Self::new_from_text(&fs::read_to_string(path)?)?;
+        if let Some(dir) = path.parent() {
+            for scenario in &mut scenarios.scenarios {
+                scenario.map = dir.join(&scenario.map);
+                if let Some(config) = &mut scenario.navigation_config {
+                    *config = dir.join(&*config);
+                }
+            }
+        }
+        Ok(scenarios)
+    }
+
+    pub fn new_from_text(source: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(source).map_err(grid_map::Error::from)?)
+    }
+
+    /// Run all the scenarios
+    pub fn run(&self) -> Result<BenchmarkReport> {
+        let results = self
+            .scenarios
+            .iter()
+            .map(run_scenario)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BenchmarkReport::new(results))
+    }
+}
+
+/// How the navigation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Reached,
+    Collided,
+    Timeout,
+    /// no global path from the start to the goal
+    NoPath,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reached => "reached",
+            Self::Collided => "collided",
+            Self::Timeout => "timeout",
+            Self::NoPath => "no_path",
+        }
+    }
+}
+
+/// Metrics of a scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub outcome: Outcome,
+    /// simulated time until the robot reaches the goal [s]
+    pub time_to_goal: Option<f64>,
+    /// length of the driven path [m]
+    pub path_length: f64,
+    /// minimum distance from the center of the robot to the obstacles [m]
+    pub min_clearance: f64,
+    /// root mean square of the linear jerk [m/s^3]
+    pub linear_jerk: f64,
+    /// root mean square of the angular jerk [rad/s^3]
+    pub angular_jerk: f64,
+    /// mean wall time of the local planning [ms]
+    pub mean_planning_latency: f64,
+    /// max wall time of the local planning [ms]
+    pub max_planning_latency: f64,
+}
+
+impl ScenarioResult {
+    fn new(name: &str, outcome: Outcome) -> Self {
+        Self {
+            name: name.to_owned(),
+            outcome,
+            time_to_goal: None,
+            path_length: 0.0,
+            min_clearance: MAX_CLEARANCE,
+            linear_jerk: 0.0,
+            angular_jerk: 0.0,
+            mean_planning_latency: 0.0,
+            max_planning_latency: 0.0,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.outcome == Outcome::Reached
+    }
+}
+
+/// Results of all the scenarios
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// ratio of the scenarios which reached the goal, in [0, 1]
+    pub success_rate: f64,
+    pub results: Vec<ScenarioResult>,
+}
+
+const CSV_HEADER: &str = "name,outcome,time_to_goal,path_length,min_clearance,linear_jerk,\
+                          angular_jerk,mean_planning_latency,max_planning_latency";
+
+impl BenchmarkReport {
+    pub fn new(results: Vec<ScenarioResult>) -> Self {
+        let success_rate = if results.is_empty() {
+            0.0
+        } else {
+            results.iter().filter(|r| r.is_success()).count() as f64 / results.len() as f64
+        };
+        Self {
+            success_rate,
+            results,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Other(e.to_string()))
+    }
+
+    /// One row per scenario. `time_to_goal` is empty if the robot didn't reach the goal.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{CSV_HEADER}\n");
+        for r in &self.results {
+            let time_to_goal = r.time_to_goal.map(|t| t.to_string()).unwrap_or_default();
+            csv += &format!(
+                "{},{},{time_to_goal},{},{},{},{},{},{}\n",
+                escape_csv(&r.name),
+                r.outcome.as_str(),
+                r.path_length,
+                r.min_clearance,
+                r.linear_jerk,
+                r.angular_jerk,
+                r.mean_planning_latency,
+                r.max_planning_latency,
+            );
+        }
+        csv
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Load the map and the navigation config of the scenario, and run it
+///
+/// The environment variables don't override the navigation config to keep the results.
+pub fn run_scenario(scenario: &Scenario) -> Result<ScenarioResult> {
+    let map = grid_map::utils::load_ros_occupancy_yaml(&scenario.map)?;
+    let config = match &scenario.navigation_config {
+        Some(path) => NavigationConfig::new_from_text(&fs::read_to_string(path)?)?,
+        None => NavigationConfig::default(),
+    };
+    run_navigation(scenario, map, &config)
+}
+
+/// Navigate from the start to the goal in [`RobotSimulator`]
+///
+/// The global path is the shortest path on the grids which are farther than the robot radius and
+/// `path_margin` from the obstacles. The `map` and `navigation_config` of the scenario are not
+/// used.
+pub fn run_navigation(
+    scenario: &Scenario,
+    map: GridMap<u8>,
+    config: &NavigationConfig,
+) -> Result<ScenarioResult> {
+    let planner = &config.local_planner;
+    let start = Pose::new(
+        na::Vector2::new(scenario.start[0], scenario.start[1]),
+        scenario.start[2],
+    );
+    let goal = Pose::new(
+        na::Vector2::new(scenario.goal[0], scenario.goal[1]),
+        scenario.goal[2],
+    );
+    let clearance = LikelihoodField::new(&map, MAX_CLEARANCE);
+    let (Some(start_grid), Some(goal_grid)) = (
+        map.to_grid(scenario.start[0], scenario.start[1]),
+        map.to_grid(scenario.goal[0], scenario.goal[1]),
+    ) else {
+        return Ok(ScenarioResult::new(&scenario.name, Outcome::NoPath));
+    };
+    let min_clearance = scenario.simulator.robot_radius + scenario.path_margin;
+    let is_free = |grid: &Grid| {
+        matches!(map.cell(grid), Some(Cell::Value(_)))
+            && map
+                .to_position(grid)
+                .is_some_and(|p| clearance.distance(p.x, p.y) > min_clearance)
+    };
+    // the clearance already keeps the path away from the corners
+    let Some(path_grids) = shortest_grid_path(&map, start_grid, goal_grid, is_free, true) else {
+        return Ok(ScenarioResult::new(&scenario.name, Outcome::NoPath));
+    };
+    let path = path_points(&map, &path_grids, scenario.goal[2]);
+
+    let names = &config.cost_maps;
+    let mut maps = HashMap::new();
+    maps.insert(
+        names.path_distance_layer.clone(),
+        path_distance_map(&map, &path_grids)?,
+    );
+    maps.insert(
+        names.goal_distance_layer.clone(),
+        goal_distance_map(&map, &goal_grid)?,
+    );
+    maps.insert(
+        names.obstacle_distance_layer.clone(),
+        obstacle_distance_map(&map)?,
+    );
+
+    let simulator = RobotSimulator::new(map, start, scenario.simulator.clone())?;
+    let dt = planner.controller_dt();
+    let max_steps = (scenario.timeout / dt).ceil() as usize;
+    let mut result = ScenarioResult::new(&scenario.name, Outcome::Timeout);
+    let mut latencies = vec![];
+    let mut velocities = vec![simulator.velocity()];
+    let mut previous = start;
+    for _ in 0..=max_steps {
+        let pose = simulator.pose();
+        let position = [pose.translation.x, pose.translation.y];
+        result.path_length += (pose.translation.vector - previous.translation.vector).norm();
+        result.min_clearance = result
+            .min_clearance
+            .min(clearance.distance(position[0], position[1]));
+        previous = pose;
+        if simulator.is_collided() {
+            result.outcome = Outcome::Collided;
+            break;
+        }
+        if is_reached(&pose, &goal, &config.goal_tolerance) {
+            result.outcome = Outcome::Reached;
+            result.time_to_goal = Some(simulator.time());
+            break;
+        }
+
+        maps.insert(
+            names.local_goal_distance_layer.clone(),
+            local_goal_distance_map(simulator.map(), &path, position, &names.local_goal)?,
+        );
+        let layered = LayeredGridMap::new(maps.clone());
+        let mut angles = HashMap::new();
+        angles.insert("rotation".to_owned(), pose.rotation.angle());
+        angles.insert("goal_direction".to_owned(), scenario.goal[2]);
+        if let Some((index, _)) = nearest_path_point(&path, position) {
+            let look_ahead = look_ahead_path_point(&path, index, config.path_look_ahead_distance);
+            angles.insert("path_direction".to_owned(), path[look_ahead][2]);
+        }
+
+        let started = Instant::now();
+        let plan = planner.plan_local_path(&pose, &simulator.velocity(), &layered, &angles);
+        latencies.push(started.elapsed().as_secs_f64() * 1000.0);
+
+        simulator
+            .send_velocity(&arci::BaseVelocity::new(
+                plan.velocity.x,
+                plan.velocity.y,
+                plan.velocity.theta,
+            ))
+            .map_err(|e| Error::Other(e.to_string()))?;
+        simulator.step(dt);
+        velocities.push(simulator.velocity());
+    }
+
+    (result.linear_jerk, result.angular_jerk) = rms_jerk(&velocities, dt);
+    if !latencies.is_empty() {
+        result.mean_planning_latency = latencies.iter().sum::<f64>() / latencies.len() as f64;
+        result.max_planning_latency = latencies.iter().copied().fold(0.0, f64::max);
+    }
+    Ok(result)
+}
+
+fn is_reached(pose: &Pose, goal: &Pose, tolerance: &GoalTolerance) -> bool {
+    (goal.translation.vector - pose.translation.vector).norm() < tolerance.distance
+        && normalize_angle(goal.rotation.angle() - pose.rotation.angle()).abs() < tolerance.angle
+}
+
+/// Positions of the grids with the direction to the next one, `[x, y, theta]`
+fn path_points(map: &GridMap<u8>, grids: &[Grid], goal_angle: f64) -> Vec<Vec<f64>> {
+    let positions = grids
+        .iter()
+        .filter_map(|grid| map.to_position(grid))
+        .collect::<Vec<_>>();
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let angle = match positions.get(i + 1) {
+                Some(next) => (next.y - p.y).atan2(next.x - p.x),
+                None => goal_angle,
+            };
+            vec![p.x, p.y, angle]
+        })
+        .collect()
+}
+
+/// Root mean square of the jerks of the linear and the angular velocities
+fn rms_jerk(velocities: &[Velocity], dt: f64) -> (f64, f64) {
+    if velocities.len() < 3 {
+        return (0.0, 0.0);
+    }
+    let jerks = velocities
+        .windows(3)
+        .map(|v| {
+            (
+                (v[2].x - 2.0 * v[1].x + v[0].x) / dt.powi(2),
+                (v[2].theta - 2.0 * v[1].theta + v[0].theta) / dt.powi(2),
+            )
+        })
+        .collect::<Vec<_>>();
+    let rms = |f: fn(&(f64, f64)) -> f64| {
+        (jerks.iter().map(|j| f(j).powi(2)).sum::<f64>() / jerks.len() as f64).sqrt()
+    };
+    (rms(|j| j.0), rms(|j| j.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIOS: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/config/benchmark/scenarios.yaml"
+    );
+
+    #[test]
+    fn benchmark_test() {
+        let scenarios = BenchmarkScenarios::new_from_file(SCENARIOS).unwrap();
+        assert_eq!(scenarios.scenarios.len(), 2);
+        let report = scenarios.run().unwrap();
+        for result in &report.results {
+            assert_eq!(result.outcome, Outcome::Reached, "{result:?}");
+            assert!(result.time_to_goal.unwrap() > 0.0);
+            assert!(result.min_clearance > 0.2, "{result:?}");
+            assert!(result.max_planning_latency >= result.mean_planning_latency);
+        }
+        assert_eq!(report.success_rate, 1.0);
+        // the detour around the block is longer
+        assert!(report.results[0].path_length > 3.8);
+        assert!(report.results[1].path_length > report.results[0].path_length);
+        let csv = report.to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("open_corridor,reached,"), "{csv}");
+        assert!(lines[2].starts_with("around_block,reached,"), "{csv}");
+    }
+
+    #[test]
+    fn no_path_test() {
+        let mut scenario = BenchmarkScenarios::new_from_file(SCENARIOS)
+            .unwrap()
+            .scenarios
+            .remove(0);
+        // in the block
+        scenario.goal = [2.2, 0.5, 0.0];
+        let result = run_scenario(&scenario).unwrap();
+        assert_eq!(result.outcome, Outcome::NoPath);
+        assert!(!result.is_success());
+        // out of the map
+        scenario.goal = [10.0, 0.5, 0.0];
+        assert_eq!(run_scenario(&scenario).unwrap().outcome, Outcome::NoPath);
+    }
+
+    #[test]
+    fn report_test() {
+        let mut reached = ScenarioResult::new("a, b", Outcome::Reached);
+        reached.time_to_goal = Some(1.5);
+        let report =
+            BenchmarkReport::new(vec![reached, ScenarioResult::new("c", Outcome::Timeout)]);
+        assert_eq!(report.success_rate, 0.5);
+        let csv = report.to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].split(',').count(), 9);
+        assert!(lines[1].starts_with("\"a, b\",reached,1.5,"));
+        assert!(lines[2].starts_with("c,timeout,,"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["success_rate"], 0.5);
+        assert_eq!(json["results"][1]["outcome"], "timeout");
+        assert!(json["results"][1]["time_to_goal"].is_null());
+
+        let velocities = [0.0, 0.0, 0.1, 0.2, 0.2].map(|x| Velocity {
+            x,
+            y: 0.0,
+            theta: 0.0,
+        });
+        let (linear, angular) = rms_jerk(&velocities, 0.1);
+        // the jerks are [10, 0, -10]
+        assert!((linear - (200.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!(angular, 0.0);
+    }
+}
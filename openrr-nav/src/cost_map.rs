@@ -1,6 +1,7 @@
 use grid_map::{Cell, Error, Grid, GridMap, Position, Result};
+use serde::{Deserialize, Serialize};
 
-use crate::utils::nearest_path_point;
+use crate::utils::{look_ahead_path_point, nearest_path_point};
 
 /// Create path distance map
 pub fn path_distance_map(map: &GridMap<u8>, path: &[Grid]) -> Result<GridMap<u8>> {
@@ -63,20 +64,50 @@ pub fn obstacle_distance_map(map: &GridMap<u8>) -> Result<GridMap<u8>> {
     Ok(distance_map)
 }
 
+/// Parameters of the local goal distance map
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct LocalGoalMapConfig {
+    /// distance along the global path from the nearest point to the local goal [m]
+    pub look_ahead_distance: f64,
+    /// width and height of the local map centered on the robot [m].
+    /// The map is fitted to the local goal if omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_size: Option<f64>,
+}
+
+impl Default for LocalGoalMapConfig {
+    fn default() -> Self {
+        Self {
+            look_ahead_distance: 1.0,
+            window_size: None,
+        }
+    }
+}
+
 /// Create local goal distance map
+///
+/// The local goal is the point on the global path which is `look_ahead_distance` ahead of
+/// the nearest point to the current pose, or the end of the path.
 pub fn local_goal_distance_map(
     map: &GridMap<u8>,
     global_path: &[Vec<f64>],
     current_pose: [f64; 2],
+    config: &LocalGoalMapConfig,
 ) -> Result<GridMap<u8>> {
-    let len = global_path.len();
-    let nearest = nearest_path_point(global_path, current_pose).unwrap();
+    let (nearest, _) = nearest_path_point(global_path, current_pose)
+        .ok_or_else(|| Error::Other("global path is empty".to_owned()))?;
 
-    const LOCAL_GOAL_FORWARD_OFFSET: usize = 20;
-    let local_goal = global_path[(nearest.0 + LOCAL_GOAL_FORWARD_OFFSET).min(len - 1)].clone();
+    let local_goal =
+        &global_path[look_ahead_path_point(global_path, nearest, config.look_ahead_distance)];
 
-    let local_width = (2. * (local_goal[0] - current_pose[0]).abs()).max(1.);
-    let local_height = (2. * (local_goal[1] - current_pose[1]).abs()).max(1.);
+    let (local_width, local_height) = match config.window_size {
+        Some(size) => (size, size),
+        None => (
+            (2. * (local_goal[0] - current_pose[0]).abs()).max(1.),
+            (2. * (local_goal[1] - current_pose[1]).abs()).max(1.),
+        ),
+    };
 
     let resolution = map.resolution();
 
@@ -90,7 +121,9 @@ pub fn local_goal_distance_map(
     );
 
     let local_map = GridMap::<u8>::new(min_point, max_point, resolution);
-    let grid = local_map.to_grid(local_goal[0], local_goal[1]).unwrap();
+    let grid = local_map
+        .to_grid(local_goal[0], local_goal[1])
+        .ok_or(Error::OutOfRangePosition(local_goal[0], local_goal[1]))?;
 
     goal_distance_map(&local_map, &grid)
 }
@@ -183,4 +216,40 @@ mod tests {
         println!("=======================");
         show_ascii_map(&obstacle_distance_map(&map).unwrap(), 0.1);
     }
+
+    #[test]
+    fn local_goal_distance_map_test() {
+        let map = grid_map::GridMap::<u8>::new(
+            Position::new(-1.05, -1.05),
+            Position::new(3.05, 1.05),
+            0.05,
+        );
+        let path = (0..=40)
+            .map(|i| vec![0.05 * i as f64, 0.0, 0.0])
+            .collect::<Vec<_>>();
+        let config = LocalGoalMapConfig::default();
+        let local_map = local_goal_distance_map(&map, &path, [0.0, 0.1], &config).unwrap();
+        let goal = local_map.to_grid(1.0, 0.0).unwrap();
+        assert_eq!(local_map.cell(&goal), Some(&Cell::Value(0)));
+
+        // look ahead beyond the end of the path
+        let config = LocalGoalMapConfig {
+            look_ahead_distance: 5.0,
+            window_size: Some(5.0),
+        };
+        let local_map = local_goal_distance_map(&map, &path, [1.5, 0.0], &config).unwrap();
+        let goal = local_map.to_grid(2.0, 0.0).unwrap();
+        assert_eq!(local_map.cell(&goal), Some(&Cell::Value(0)));
+
+        // the goal is outside of the window
+        let config = LocalGoalMapConfig {
+            look_ahead_distance: 1.0,
+            window_size: Some(0.5),
+        };
+        assert!(matches!(
+            local_goal_distance_map(&map, &path, [0.0, 0.0], &config),
+            Err(grid_map::Error::OutOfRangePosition(..))
+        ));
+        assert!(local_goal_distance_map(&map, &[], [0.0, 0.0], &config).is_err());
+    }
 }
@@ -0,0 +1,605 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{utils::shortest_grid_path, Error, LikelihoodField, Pose, Result, RobotPath};
+
+/// Parameters of [`CoveragePlanner`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct CoverageConfig {
+    /// width covered by the tool in a sweep, which is also the interval of the sweep lines [m]
+    pub tool_width: f64,
+    /// direction of the sweep lines [rad]
+    pub heading: f64,
+    /// the robot keeps this distance from the obstacles, e.g. the robot radius [m]
+    pub obstacle_margin: f64,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            tool_width: 0.3,
+            heading: 0.0,
+            obstacle_margin: 0.0,
+        }
+    }
+}
+
+impl CoverageConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.tool_width.is_finite() && self.tool_width > 0.0) {
+            return Err(Error::invalid_config(
+                "tool_width",
+                format!("must be positive, but {}", self.tool_width),
+            ));
+        }
+        if !self.heading.is_finite() {
+            return Err(Error::invalid_config(
+                "heading",
+                format!("must be finite, but {}", self.heading),
+            ));
+        }
+        if !(self.obstacle_margin.is_finite() && self.obstacle_margin >= 0.0) {
+            return Err(Error::invalid_config(
+                "obstacle_margin",
+                format!("must be non-negative, but {}", self.obstacle_margin),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Free part of a sweep line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepSegment {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Boustrophedon cell, whose sweep segments are connected one by one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageCell {
+    /// segments on the adjacent sweep lines, in the order of the lines
+    pub segments: Vec<SweepSegment>,
+}
+
+/// Result of [`CoveragePlanner::plan`]
+#[derive(Debug, Clone)]
+pub struct CoveragePlan {
+    /// cells in the order of the visit
+    pub cells: Vec<CoverageCell>,
+    /// cells which can't be reached from the start
+    pub unreachable_cells: Vec<CoverageCell>,
+    pub path: RobotPath,
+}
+
+/// Coverage path planner by the boustrophedon cell decomposition
+///
+/// The free space (`Cell::Value`) is swept by the lines of `heading` at the interval of
+/// `tool_width`. The free parts of the lines are split into the cells where the number of the
+/// parts changes, which happens at the obstacles. Each cell is covered back and forth, and the
+/// nearest cell is visited next through the shortest path on the grid.
+#[derive(Debug, Clone)]
+pub struct CoveragePlanner {
+    config: CoverageConfig,
+}
+
+/// Free interval on a sweep line in the frame rotated by the heading
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    v: f64,
+    u_min: f64,
+    u_max: f64,
+}
+
+impl CoveragePlanner {
+    pub fn new(config: CoverageConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self { config })
+    }
+
+    pub fn config(&self) -> &CoverageConfig {
+        &self.config
+    }
+
+    /// Decompose the free space into the cells
+    pub fn decompose(&self, map: &GridMap<u8>) -> Vec<CoverageCell> {
+        self.decompose_free_cells(map, &self.free_cells(map))
+    }
+
+    fn decompose_free_cells(&self, map: &GridMap<u8>, is_free: &[bool]) -> Vec<CoverageCell> {
+        let lines = self.sweep_lines(map, is_free);
+        let rotation = na::Rotation2::new(self.config.heading);
+        let to_position = |v: f64, u: f64| {
+            let p = rotation * na::Vector2::new(u, v);
+            Position::new(p.x, p.y)
+        };
+        // (cell index, interval) on the previous line
+        let mut previous: Vec<(usize, Interval)> = vec![];
+        let mut cells: Vec<CoverageCell> = vec![];
+        for line in lines {
+            let mut current = vec![];
+            for interval in &line {
+                let overlapping = previous
+                    .iter()
+                    .filter(|(_, p)| overlaps(p, interval))
+                    .collect::<Vec<_>>();
+                let continued = match overlapping.as_slice() {
+                    [(cell, p)] if line.iter().filter(|i| overlaps(p, i)).count() == 1 => {
+                        Some(*cell)
+                    }
+                    _ => None,
+                };
+                let cell = continued.unwrap_or_else(|| {
+                    cells.push(CoverageCell::default());
+                    cells.len() - 1
+                });
+                cells[cell].segments.push(SweepSegment {
+                    start: to_position(interval.v, interval.u_min),
+                    end: to_position(interval.v, interval.u_max),
+                });
+                current.push((cell, *interval));
+            }
+            previous = current;
+        }
+        cells
+    }
+
+    /// Plan the path to cover the free space from the start
+    pub fn plan(&self, map: &GridMap<u8>, start: &Position) -> Result<CoveragePlan> {
+        if map.to_grid(start.x, start.y).is_none() {
+            return Err(grid_map::Error::OutOfRangePosition(start.x, start.y).into());
+        }
+        let is_free = self.free_cells(map);
+        let direction = na::Vector2::new(self.config.heading.cos(), self.config.heading.sin());
+
+        let mut remaining = self.decompose_free_cells(map, &is_free);
+        let mut cells = vec![];
+        let mut unreachable_cells = vec![];
+        let mut waypoints = vec![*start];
+        let mut current = *start;
+        while !remaining.is_empty() {
+            // the nearest entry among the four corners of the cells
+            let (index, reverse_lines, reverse_first) = remaining
+                .iter()
+                .enumerate()
+                .flat_map(|(i, cell)| {
+                    let first = cell.segments[0];
+                    let last = cell.segments[cell.segments.len() - 1];
+                    [
+                        (i, false, false, first.start),
+                        (i, false, true, first.end),
+                        (i, true, false, last.start),
+                        (i, true, true, last.end),
+                    ]
+                })
+                .min_by(|a, b| distance(&current, &a.3).total_cmp(&distance(&current, &b.3)))
+                .map(|(i, reverse_lines, reverse_first, _)| (i, reverse_lines, reverse_first))
+                .unwrap();
+            let cell = remaining.remove(index);
+            let mut sweeps = cell.segments.clone();
+            if reverse_lines {
+                sweeps.reverse();
+            }
+            let entry = if reverse_first {
+                sweeps[0].end
+            } else {
+                sweeps[0].start
+            };
+            let mut cell_waypoints = vec![];
+            if !connect(
+                map,
+                &is_free,
+                &direction,
+                &current,
+                &entry,
+                &mut cell_waypoints,
+            ) {
+                unreachable_cells.push(cell);
+                continue;
+            }
+            let mut visited = sweeps.len();
+            for (i, sweep) in sweeps.iter().enumerate() {
+                let (from, to) = if (i % 2 == 0) != reverse_first {
+                    (sweep.start, sweep.end)
+                } else {
+                    (sweep.end, sweep.start)
+                };
+                let last = cell_waypoints[cell_waypoints.len() - 1];
+                if i > 0 && !connect(map, &is_free, &direction, &last, &from, &mut cell_waypoints) {
+                    // separated by a thin obstacle between the lines, visit the rest later
+                    visited = i;
+                    break;
+                }
+                cell_waypoints.push(to);
+            }
+            if visited < sweeps.len() {
+                remaining.push(CoverageCell {
+                    segments: sweeps.split_off(visited),
+                });
+            }
+            waypoints.extend(cell_waypoints);
+            current = waypoints[waypoints.len() - 1];
+            cells.push(CoverageCell { segments: sweeps });
+        }
+        if cells.is_empty() && unreachable_cells.is_empty() {
+            return Err(Error::Other("no free space to cover".to_owned()));
+        }
+        Ok(CoveragePlan {
+            cells,
+            unreachable_cells,
+            path: densify(&waypoints, map.resolution()),
+        })
+    }
+
+    /// Free cells away from the obstacles by `obstacle_margin`
+    fn free_cells(&self, map: &GridMap<u8>) -> Vec<bool> {
+        let clearance = (self.config.obstacle_margin > 0.0)
+            .then(|| LikelihoodField::new(map, self.config.obstacle_margin + map.resolution()));
+        let width = map.width();
+        map.cells()
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                if !matches!(cell, Cell::Value(_)) {
+                    return false;
+                }
+                let Some(clearance) = &clearance else {
+                    return true;
+                };
+                map.to_position(&Grid::new(index % width, index / width))
+                    .is_some_and(|p| clearance.distance(p.x, p.y) > self.config.obstacle_margin)
+            })
+            .collect()
+    }
+
+    /// Free intervals on each sweep line, from the minimum `v` in the rotated frame
+    fn sweep_lines(&self, map: &GridMap<u8>, is_free: &[bool]) -> Vec<Vec<Interval>> {
+        let width = map.width();
+        let resolution = map.resolution();
+        let inverse = na::Rotation2::new(-self.config.heading);
+        let rotation = inverse.inverse();
+        // range of the free space in the rotated frame
+        let half_diagonal = resolution * std::f64::consts::FRAC_1_SQRT_2;
+        let mut u_range = (f64::MAX, f64::MIN);
+        let mut v_range = (f64::MAX, f64::MIN);
+        for (index, _) in is_free.iter().enumerate().filter(|(_, free)| **free) {
+            let Some(p) = map.to_position(&Grid::new(index % width, index / width)) else {
+                continue;
+            };
+            let local = inverse * na::Vector2::new(p.x, p.y);
+            u_range = (u_range.0.min(local.x), u_range.1.max(local.x));
+            v_range = (v_range.0.min(local.y), v_range.1.max(local.y));
+        }
+        if u_range.0 > u_range.1 {
+            return vec![];
+        }
+        let tool_width = self.config.tool_width;
+        let (v_min, v_max) = (v_range.0 - resolution / 2.0, v_range.1 + resolution / 2.0);
+        let num_lines = ((v_max - v_min) / tool_width).ceil().max(1.0) as usize;
+        let step = resolution / 2.0;
+        let u_min = u_range.0 - half_diagonal;
+        let num_samples = ((u_range.1 + half_diagonal - u_min) / step).ceil() as usize + 1;
+        (0..num_lines)
+            .map(|k| {
+                // keep the last line inside
+                let v = if v_max - v_min <= tool_width {
+                    (v_min + v_max) / 2.0
+                } else {
+                    (v_min + tool_width * (k as f64 + 0.5)).min(v_max - tool_width / 2.0)
+                };
+                let mut intervals: Vec<Interval> = vec![];
+                let mut run: Option<(f64, f64)> = None;
+                for i in 0..num_samples {
+                    let u = u_min + step * i as f64;
+                    let p = rotation * na::Vector2::new(u, v);
+                    let free = map
+                        .to_grid(p.x, p.y)
+                        .is_some_and(|g| is_free[g.y * width + g.x]);
+                    run = match (run, free) {
+                        (Some((start, _)), true) => Some((start, u)),
+                        (None, true) => Some((u, u)),
+                        (Some((start, end)), false) => {
+                            intervals.push(Interval {
+                                v,
+                                u_min: start,
+                                u_max: end,
+                            });
+                            None
+                        }
+                        (None, false) => None,
+                    };
+                }
+                if let Some((start, end)) = run {
+                    intervals.push(Interval {
+                        v,
+                        u_min: start,
+                        u_max: end,
+                    });
+                }
+                intervals
+            })
+            .collect()
+    }
+}
+
+/// Append the waypoints to go from `from` to `to` without passing the obstacles
+///
+/// The straight line is used if it is free, then the two lines along and across the sweep
+/// `direction`, and the shortest path on the grid otherwise. Returns false if unreachable.
+fn connect(
+    map: &GridMap<u8>,
+    is_free: &[bool],
+    direction: &na::Vector2<f64>,
+    from: &Position,
+    to: &Position,
+    waypoints: &mut Vec<Position>,
+) -> bool {
+    let is_free_grid = |grid: &Grid| is_free[grid.y * map.width() + grid.x];
+    let is_clear = |a: &Position, b: &Position| {
+        // the interpolated points of `densify` are also checked, since the ray can pass
+        // between the cells at their corner
+        let num = 4 * (distance(a, b) / map.resolution()).ceil() as usize;
+        map.raytrace(a, b).iter().all(is_free_grid)
+            && (0..=num).all(|i| {
+                let t = if num == 0 { 0.0 } else { i as f64 / num as f64 };
+                map.to_grid(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+                    .is_some_and(|g| is_free_grid(&g))
+            })
+    };
+    if is_clear(from, to) {
+        waypoints.push(*to);
+        return true;
+    }
+    let along = direction.dot(&na::Vector2::new(to.x - from.x, to.y - from.y));
+    let corners = [
+        Position::new(from.x + direction.x * along, from.y + direction.y * along),
+        Position::new(to.x - direction.x * along, to.y - direction.y * along),
+    ];
+    if let Some(corner) = corners
+        .iter()
+        .find(|corner| is_clear(from, corner) && is_clear(corner, to))
+    {
+        waypoints.extend([*corner, *to]);
+        return true;
+    }
+    let path = map.to_grid(from.x, from.y).and_then(|start| {
+        let goal = map.to_grid(to.x, to.y)?;
+        shortest_grid_path(map, start, goal, is_free_grid, false)
+    });
+    let Some(path) = path else {
+        return false;
+    };
+    // skip the cells of `from` and `to`
+    let len = path.len();
+    waypoints.extend(
+        path.into_iter()
+            .take(len.saturating_sub(1))
+            .skip(1)
+            .filter_map(|g| map.to_position(&g)),
+    );
+    waypoints.push(*to);
+    true
+}
+
+fn overlaps(a: &Interval, b: &Interval) -> bool {
+    a.u_min <= b.u_max && b.u_min <= a.u_max
+}
+
+fn distance(a: &Position, b: &Position) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+/// Interpolate the waypoints at the interval, facing the direction of the motion
+fn densify(waypoints: &[Position], interval: f64) -> RobotPath {
+    let mut path = RobotPath::new();
+    let mut angle = 0.0;
+    for (i, p) in waypoints.iter().enumerate() {
+        let Some(next) = waypoints.get(i + 1) else {
+            path.push(Pose::new(na::Vector2::new(p.x, p.y), angle));
+            break;
+        };
+        let length = distance(p, next);
+        if length < 1e-9 {
+            continue;
+        }
+        angle = (next.y - p.y).atan2(next.x - p.x);
+        let num = (length / interval).ceil() as usize;
+        for j in 0..num {
+            let t = j as f64 / num as f64;
+            path.push(Pose::new(
+                na::Vector2::new(p.x + (next.x - p.x) * t, p.y + (next.y - p.y) * t),
+                angle,
+            ));
+        }
+    }
+    path
+}
+
+/// Percentage of the free cells (`Cell::Value`) covered by the tool along the path
+///
+/// A cell is covered if its center is within `tool_width / 2` from the path.
+pub fn coverage_percentage(map: &GridMap<u8>, path: &RobotPath, tool_width: f64) -> f64 {
+    let radius = tool_width / 2.0;
+    let resolution = map.resolution();
+    let mut covered = vec![false; map.len()];
+    let points = path
+        .0
+        .iter()
+        .map(|pose| na::Point2::new(pose.translation.x, pose.translation.y))
+        .collect::<Vec<_>>();
+    let segments = points
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .chain(points.first().map(|p| (*p, *p)));
+    for (a, b) in segments {
+        let to_grid = |x: f64, y: f64| {
+            (
+                ((x - map.min_point().x) / resolution).floor().max(0.0) as usize,
+                ((y - map.min_point().y) / resolution).floor().max(0.0) as usize,
+            )
+        };
+        let (min_x, min_y) = to_grid(a.x.min(b.x) - radius, a.y.min(b.y) - radius);
+        let (max_x, max_y) = to_grid(a.x.max(b.x) + radius, a.y.max(b.y) + radius);
+        for y in min_y..=max_y.min(map.height().saturating_sub(1)) {
+            for x in min_x..=max_x.min(map.width().saturating_sub(1)) {
+                let Some(center) = map.to_position(&Grid::new(x, y)) else {
+                    continue;
+                };
+                let center = na::Point2::new(center.x, center.y);
+                let ab = b - a;
+                let t = if ab.norm_squared() > 0.0 {
+                    ((center - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                if (a + ab * t - center).norm() <= radius + 1e-9 {
+                    covered[y * map.width() + x] = true;
+                }
+            }
+        }
+    }
+    let free = map
+        .cells()
+        .iter()
+        .filter(|c| matches!(c, Cell::Value(_)))
+        .count();
+    if free == 0 {
+        return 100.0;
+    }
+    let covered_free = map
+        .cells()
+        .iter()
+        .zip(&covered)
+        .filter(|(c, covered)| matches!(c, Cell::Value(_)) && **covered)
+        .count();
+    covered_free as f64 / free as f64 * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// room of 4m x 3m surrounded by the walls, with a block in the middle
+    fn new_map(block: bool) -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(4.0, 3.0), 0.05);
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let grid = Grid::new(x, y);
+                let wall = x == 0 || y == 0 || x == map.width() - 1 || y == map.height() - 1;
+                let in_block = (30..50).contains(&x) && (20..40).contains(&y);
+                if wall || (block && in_block) {
+                    map.set_obstacle(&grid).unwrap();
+                } else {
+                    map.set_value(&grid, 0).unwrap();
+                }
+            }
+        }
+        map
+    }
+
+    fn assert_path_is_free(map: &GridMap<u8>, path: &RobotPath) {
+        for pose in &path.0 {
+            let grid = map.to_grid(pose.translation.x, pose.translation.y).unwrap();
+            assert!(
+                matches!(map.cell(&grid), Some(Cell::Value(_))),
+                "{:?}",
+                pose.translation
+            );
+        }
+    }
+
+    #[test]
+    fn coverage_empty_room_test() {
+        let map = new_map(false);
+        let planner = CoveragePlanner::new(Default::default()).unwrap();
+        let plan = planner.plan(&map, &Position::new(0.5, 0.5)).unwrap();
+        assert_eq!(plan.cells.len(), 1);
+        assert!(plan.unreachable_cells.is_empty());
+        // 2.9m / 0.3m
+        assert_eq!(plan.cells[0].segments.len(), 10);
+        assert_path_is_free(&map, &plan.path);
+        let coverage = coverage_percentage(&map, &plan.path, 0.3);
+        assert!((coverage - 100.0).abs() < 1e-9, "{coverage}");
+        // the gaps between the sweeps
+        assert!(coverage_percentage(&map, &plan.path, 0.1) < 50.0);
+        assert_eq!(coverage_percentage(&map, &RobotPath::new(), 0.3), 0.0);
+        // sweep along x
+        let along_x = plan
+            .path
+            .0
+            .iter()
+            .filter(|pose| pose.rotation.angle().sin().abs() < 1e-9)
+            .count();
+        assert!(along_x as f64 > plan.path.0.len() as f64 * 0.8, "{along_x}");
+    }
+
+    #[test]
+    fn coverage_with_obstacle_test() {
+        let map = new_map(true);
+        let planner = CoveragePlanner::new(Default::default()).unwrap();
+        let cells = planner.decompose(&map);
+        // below, left, right and above the block
+        assert_eq!(cells.len(), 4);
+        let plan = planner.plan(&map, &Position::new(0.5, 0.5)).unwrap();
+        assert_eq!(plan.cells.len(), 4);
+        assert_path_is_free(&map, &plan.path);
+        let coverage = coverage_percentage(&map, &plan.path, 0.3);
+        assert!(coverage > 97.0, "{coverage}");
+        // the first cell contains the start
+        let first = plan.cells[0].segments[0];
+        assert!(first.start.y < 0.5 && first.start.x < 0.5);
+        // all the cells are connected by the path without jumps
+        for pose in plan.path.0.windows(2) {
+            let step = (pose[1].translation.vector - pose[0].translation.vector).norm();
+            assert!(step <= map.resolution() + 1e-9, "{step}");
+        }
+
+        // diagonal sweeps
+        let planner = CoveragePlanner::new(CoverageConfig {
+            heading: std::f64::consts::FRAC_PI_4,
+            ..Default::default()
+        })
+        .unwrap();
+        let plan = planner.plan(&map, &Position::new(0.5, 0.5)).unwrap();
+        assert_path_is_free(&map, &plan.path);
+        let coverage = coverage_percentage(&map, &plan.path, 0.3);
+        assert!(coverage > 90.0, "{coverage}");
+    }
+
+    #[test]
+    fn coverage_margin_test() {
+        let map = new_map(true);
+        let planner = CoveragePlanner::new(CoverageConfig {
+            obstacle_margin: 0.2,
+            ..Default::default()
+        })
+        .unwrap();
+        let plan = planner.plan(&map, &Position::new(0.5, 0.5)).unwrap();
+        let clearance = LikelihoodField::new(&map, 1.0);
+        for pose in &plan.path.0[1..] {
+            let distance = clearance.distance(pose.translation.x, pose.translation.y);
+            assert!(distance > 0.2 - 1e-9, "{:?} {distance}", pose.translation);
+        }
+        // the cells near the walls are not covered
+        let coverage = coverage_percentage(&map, &plan.path, 0.3);
+        assert!(coverage < 97.0 && coverage > 70.0, "{coverage}");
+    }
+
+    #[test]
+    fn coverage_config_test() {
+        let config = CoverageConfig {
+            tool_width: 0.0,
+            ..Default::default()
+        };
+        assert!(CoveragePlanner::new(config).is_err());
+        let planner = CoveragePlanner::new(Default::default()).unwrap();
+        assert!(planner
+            .plan(&new_map(false), &Position::new(-1.0, 0.5))
+            .is_err());
+        let config: CoverageConfig = serde_yaml::from_str("heading: 1.0").unwrap();
+        assert_eq!(config.heading, 1.0);
+        assert_eq!(config.tool_width, 0.3);
+    }
+}
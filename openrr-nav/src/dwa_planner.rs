@@ -2,16 +2,28 @@ use grid_map::{Cell, GridMap, LayeredGridMap, Position};
 pub use na::Vector2;
 use nalgebra as na;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::Path};
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
 
-use crate::Error;
+use crate::{Error, MovingObstacle};
 
+mod critic;
+mod motion_model;
 mod serde_cost_name_weight;
+mod velocity_profile;
 
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
-#[serde(deny_unknown_fields, from = "[f64; 2]", into = "[f64; 2]")]
+pub use critic::*;
+pub use motion_model::*;
+pub use velocity_profile::*;
+
+/// Velocity of the robot
+///
+/// It is written as `[x, theta]` or `[x, y, theta]` in the config.
+/// `y` is used only by holonomic (omnidirectional) robots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, try_from = "Vec<f64>", into = "Vec<f64>")]
 pub struct Velocity {
     pub x: f64,
+    pub y: f64,
     pub theta: f64,
 }
 
@@ -19,21 +31,55 @@ impl From<[f64; 2]> for Velocity {
     fn from(value: [f64; 2]) -> Self {
         Self {
             x: value[0],
+            y: 0.0,
             theta: value[1],
         }
     }
 }
 
-impl From<Velocity> for [f64; 2] {
+impl From<[f64; 3]> for Velocity {
+    fn from(value: [f64; 3]) -> Self {
+        Self {
+            x: value[0],
+            y: value[1],
+            theta: value[2],
+        }
+    }
+}
+
+impl TryFrom<Vec<f64>> for Velocity {
+    type Error = String;
+
+    fn try_from(value: Vec<f64>) -> Result<Self, Self::Error> {
+        match value[..] {
+            [x, theta] => Ok([x, theta].into()),
+            [x, y, theta] => Ok([x, y, theta].into()),
+            _ => Err(format!(
+                "velocity must be [x, theta] or [x, y, theta], but has {} elements",
+                value.len()
+            )),
+        }
+    }
+}
+
+impl From<Velocity> for Vec<f64> {
     fn from(value: Velocity) -> Self {
-        [value.x, value.theta]
+        if value.y == 0.0 {
+            vec![value.x, value.theta]
+        } else {
+            vec![value.x, value.y, value.theta]
+        }
     }
 }
 
+/// Acceleration of the robot
+///
+/// It is written as `[x, theta]` or `[x, y, theta]` in the config.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
-#[serde(deny_unknown_fields, from = "[f64; 2]", into = "[f64; 2]")]
+#[serde(deny_unknown_fields, try_from = "Vec<f64>", into = "Vec<f64>")]
 pub struct Acceleration {
     pub x: f64,
+    pub y: f64,
     pub theta: f64,
 }
 
@@ -41,21 +87,54 @@ impl From<[f64; 2]> for Acceleration {
     fn from(value: [f64; 2]) -> Self {
         Self {
             x: value[0],
+            y: 0.0,
             theta: value[1],
         }
     }
 }
 
-impl From<Acceleration> for [f64; 2] {
+impl From<[f64; 3]> for Acceleration {
+    fn from(value: [f64; 3]) -> Self {
+        Self {
+            x: value[0],
+            y: value[1],
+            theta: value[2],
+        }
+    }
+}
+
+impl TryFrom<Vec<f64>> for Acceleration {
+    type Error = String;
+
+    fn try_from(value: Vec<f64>) -> Result<Self, Self::Error> {
+        match value[..] {
+            [x, theta] => Ok([x, theta].into()),
+            [x, y, theta] => Ok([x, y, theta].into()),
+            _ => Err(format!(
+                "acceleration must be [x, theta] or [x, y, theta], but has {} elements",
+                value.len()
+            )),
+        }
+    }
+}
+
+impl From<Acceleration> for Vec<f64> {
     fn from(value: Acceleration) -> Self {
-        [value.x, value.theta]
+        if value.y == 0.0 {
+            vec![value.x, value.theta]
+        } else {
+            vec![value.x, value.y, value.theta]
+        }
     }
 }
 
 pub type Pose = na::Isometry2<f64>;
 
 fn velocity_to_pose(velocity: &Velocity, dt: f64) -> Pose {
-    Pose::new(na::Vector2::new(velocity.x * dt, 0.0), velocity.theta * dt)
+    Pose::new(
+        na::Vector2::new(velocity.x * dt, velocity.y * dt),
+        velocity.theta * dt,
+    )
 }
 
 #[derive(Debug, Clone, Default)]
@@ -63,6 +142,9 @@ pub struct Plan {
     pub velocity: Velocity,
     pub cost: f64,
     pub path: Vec<Pose>,
+    /// Weighted cost of each critic. The sum of them is `cost`, unless a critic rejects the plan
+    /// and both of them are `f64::MAX`.
+    pub cost_breakdown: HashMap<String, f64>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -81,6 +163,87 @@ pub struct Limits {
     pub min_accel: Acceleration,
 }
 
+impl Limits {
+    /// Check that the min limits are not above the max limits
+    pub fn validate(&self) -> Result<(), Error> {
+        let axes = [
+            (
+                "x",
+                self.min_velocity.x,
+                self.max_velocity.x,
+                self.min_accel.x,
+                self.max_accel.x,
+            ),
+            (
+                "y",
+                self.min_velocity.y,
+                self.max_velocity.y,
+                self.min_accel.y,
+                self.max_accel.y,
+            ),
+            (
+                "theta",
+                self.min_velocity.theta,
+                self.max_velocity.theta,
+                self.min_accel.theta,
+                self.max_accel.theta,
+            ),
+        ];
+        for (axis, min_velocity, max_velocity, min_accel, max_accel) in axes {
+            if min_velocity > max_velocity {
+                return Err(Error::invalid_config(
+                    format!("limits.min_velocity.{axis}"),
+                    format!("{min_velocity} is above max_velocity {max_velocity}"),
+                ));
+            }
+            if min_accel > 0.0 {
+                return Err(Error::invalid_config(
+                    format!("limits.min_acceleration.{axis}"),
+                    format!("must not be positive, but {min_accel}"),
+                ));
+            }
+            if max_accel < 0.0 {
+                return Err(Error::invalid_config(
+                    format!("limits.max_acceleration.{axis}"),
+                    format!("must not be negative, but {max_accel}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a cell without a usable value is charged in the cost layers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellPolicy {
+    /// Reject the trajectory which passes through the cell
+    Lethal,
+    /// Charge a fixed cost for the cell
+    Cost(f64),
+    /// Don't charge anything for the cell
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+/// Policies for Unknown and Uninitialized cells in the cost layers
+pub struct CellPolicies {
+    /// policy for `Cell::Unknown`
+    pub unknown: CellPolicy,
+    /// policy for `Cell::Uninitialized`
+    pub uninitialized: CellPolicy,
+}
+
+impl Default for CellPolicies {
+    fn default() -> Self {
+        Self {
+            unknown: CellPolicy::Cost(255.0),
+            uninitialized: CellPolicy::Lethal,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 /// DWA Planner
@@ -91,6 +254,16 @@ pub struct DwaPlanner {
     controller_dt: f64,
     simulation_duration: f64,
     num_vel_sample: i32,
+    #[serde(default)]
+    cell_policies: CellPolicies,
+    #[serde(default)]
+    motion_model: MotionModel,
+    #[serde(default)]
+    velocity_profile: VelocityProfile,
+    #[serde(default = "CriticConfig::default_critics")]
+    critics: Vec<CriticConfig>,
+    #[serde(skip)]
+    custom_critics: CustomCritics,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -100,22 +273,35 @@ struct DwaPlannerConfig {
     dwa_planner: DwaPlanner,
 }
 
-fn accumulate_values_by_positions(map: &GridMap<u8>, positions: &[Position]) -> f64 {
-    if positions.is_empty() {
+fn accumulate_values_by_positions(
+    map: &GridMap<u8>,
+    positions: impl IntoIterator<Item = Position>,
+    cell_policies: &CellPolicies,
+) -> f64 {
+    let mut positions = positions.into_iter().peekable();
+    if positions.peek().is_none() {
         return f64::MAX;
     }
     let mut cost: f64 = 0.0;
     for p in positions {
-        // TODO: Support allow Unknown
         if let Some(grid) = map.to_grid(p.x, p.y) {
             if let Some(cell) = map.cell(&grid) {
-                match cell {
+                let policy = match cell {
                     Cell::Value(v) => {
                         cost += *v as f64;
+                        continue;
+                    }
+                    Cell::Obstacle => {
+                        cost += 255.0;
+                        continue;
                     }
-                    Cell::Uninitialized => panic!("Uninitialized is not supported!"),
-                    Cell::Obstacle => cost += 255.0,
-                    Cell::Unknown => cost += 255.0,
+                    Cell::Uninitialized => cell_policies.uninitialized,
+                    Cell::Unknown => cell_policies.unknown,
+                };
+                match policy {
+                    CellPolicy::Lethal => return f64::MAX,
+                    CellPolicy::Cost(c) => cost += c,
+                    CellPolicy::Ignore => {}
                 }
             } else {
                 // out of grid (should not happen)
@@ -129,6 +315,79 @@ fn accumulate_values_by_positions(map: &GridMap<u8>, positions: &[Position]) ->
     cost
 }
 
+/// Select the plan with the minimum cost. Returns a default plan with `f64::MAX` cost
+/// if all the plans are rejected.
+pub fn min_cost_plan(plans: impl IntoIterator<Item = Plan>) -> Plan {
+    let mut selected_plan = Plan {
+        cost: f64::MAX,
+        ..Default::default()
+    };
+    for plan in plans {
+        if plan.cost < selected_plan.cost {
+            selected_plan = plan;
+        }
+    }
+    selected_plan
+}
+
+/// Plans scored by the critics, whose weighted costs are kept in a flat buffer
+///
+/// The cost breakdowns with the names are built only for the plans which are returned.
+struct ScoredCandidates<'a> {
+    /// names of the critics in the order of the costs in a row
+    names: Vec<&'a str>,
+    plans: Vec<Plan>,
+    /// weighted costs of the critics, `row_len` for each plan
+    costs: Vec<f64>,
+    row_len: usize,
+}
+
+impl ScoredCandidates<'_> {
+    fn breakdown(&self, index: usize) -> HashMap<String, f64> {
+        let row = &self.costs[index * self.row_len..(index + 1) * self.row_len];
+        self.names
+            .iter()
+            .zip(row)
+            .map(|(name, cost)| ((*name).to_owned(), *cost))
+            .collect()
+    }
+
+    /// All the plans with their cost breakdowns
+    fn into_plans(self) -> Vec<Plan> {
+        let breakdowns = (0..self.plans.len())
+            .map(|i| self.breakdown(i))
+            .collect::<Vec<_>>();
+        self.plans
+            .into_iter()
+            .zip(breakdowns)
+            .map(|(plan, cost_breakdown)| Plan {
+                cost_breakdown,
+                ..plan
+            })
+            .collect()
+    }
+
+    /// The plan selected by [`min_cost_plan`], with its cost breakdown
+    fn into_min_cost_plan(mut self) -> Plan {
+        // the first one among the same costs, and never the rejected one
+        let index = self
+            .plans
+            .iter()
+            .enumerate()
+            .filter(|(_, plan)| plan.cost < f64::MAX)
+            .min_by(|(_, a), (_, b)| a.cost.total_cmp(&b.cost))
+            .map(|(index, _)| index);
+        let Some(index) = index else {
+            return min_cost_plan([]);
+        };
+        let cost_breakdown = self.breakdown(index);
+        Plan {
+            cost_breakdown,
+            ..self.plans.swap_remove(index)
+        }
+    }
+}
+
 impl DwaPlanner {
     pub fn new(
         limits: Limits,
@@ -143,22 +402,142 @@ impl DwaPlanner {
             controller_dt,
             simulation_duration,
             num_vel_sample,
+            cell_policies: CellPolicies::default(),
+            motion_model: MotionModel::default(),
+            velocity_profile: VelocityProfile::default(),
+            critics: CriticConfig::default_critics(),
+            custom_critics: CustomCritics::default(),
         }
     }
 
     pub fn new_from_config(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let source = fs::read_to_string(path).unwrap();
+        let source = fs::read_to_string(path)?;
         Self::new_from_config_text(&source)
     }
 
+    /// Parse and validate the config
+    ///
+    /// Every weight must have a critic in the config. Use
+    /// [`DwaPlanner::new_from_config_text_with_critics`] to weight the custom critics.
     pub fn new_from_config_text(source: &str) -> Result<Self, Error> {
+        Self::new_from_config_text_with_critics(source, [])
+    }
+
+    /// Parse the config, register the custom critics, and validate it
+    ///
+    /// The weights can refer to both of the critics in the config and `critics`.
+    pub fn new_from_config_text_with_critics(
+        source: &str,
+        critics: impl IntoIterator<Item = Arc<dyn TrajectoryCritic>>,
+    ) -> Result<Self, Error> {
         use serde_yaml::from_str;
         let config: DwaPlannerConfig = from_str(source).map_err(grid_map::Error::from)?;
-        Ok(config.dwa_planner)
+        let mut planner = config.dwa_planner;
+        planner.validate()?;
+        planner.custom_critics.0.extend(critics);
+        planner.validate_critics()?;
+        Ok(planner)
+    }
+
+    /// Serialize the config to YAML which can be loaded by [`DwaPlanner::new_from_config_text`]
+    ///
+    /// The critics registered by [`DwaPlanner::add_critic`] are not included.
+    pub fn to_config_text(&self) -> Result<String, Error> {
+        let config = DwaPlannerConfig {
+            dwa_planner: self.clone(),
+        };
+        Ok(serde_yaml::to_string(&config).map_err(grid_map::Error::from)?)
+    }
+
+    /// Write the config to the file
+    pub fn save_config(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        fs::write(path, self.to_config_text()?)?;
+        Ok(())
+    }
+
+    /// Check that the parameters can be used for planning
+    ///
+    /// The critics of the weights are not checked, because the custom critics can be registered
+    /// later. See [`DwaPlanner::validate_critics`].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.controller_dt <= 0.0 || !self.controller_dt.is_finite() {
+            return Err(Error::invalid_config(
+                "controller_dt",
+                format!("must be positive, but {}", self.controller_dt),
+            ));
+        }
+        if self.simulation_duration < self.controller_dt || !self.simulation_duration.is_finite() {
+            return Err(Error::invalid_config(
+                "simulation_duration",
+                format!(
+                    "must not be shorter than controller_dt ({}), but {}",
+                    self.controller_dt, self.simulation_duration
+                ),
+            ));
+        }
+        if self.num_vel_sample <= 0 {
+            return Err(Error::invalid_config(
+                "num_vel_sample",
+                format!("must be positive, but {}", self.num_vel_sample),
+            ));
+        }
+        self.limits.validate()?;
+        for (name, weight) in &self.cost_name_weight {
+            if !weight.is_finite() {
+                return Err(Error::invalid_config(
+                    format!("cost_name_weight.{name}"),
+                    format!("must be finite, but {weight}"),
+                ));
+            }
+        }
+        for (field, policy) in [
+            ("cell_policies.unknown", self.cell_policies.unknown),
+            (
+                "cell_policies.uninitialized",
+                self.cell_policies.uninitialized,
+            ),
+        ] {
+            if let CellPolicy::Cost(cost) = policy {
+                if cost < 0.0 || !cost.is_finite() {
+                    return Err(Error::invalid_config(
+                        field,
+                        format!("cost must be non-negative, but {cost}"),
+                    ));
+                }
+            }
+        }
+        self.motion_model.validate()?;
+        self.velocity_profile.validate()?;
+        Ok(())
+    }
+
+    /// Check that every weight has a critic, after the custom critics are registered
+    pub fn validate_critics(&self) -> Result<(), Error> {
+        for name in self.cost_name_weight.keys() {
+            if self.critic(name).is_none() {
+                return Err(Error::invalid_config(
+                    format!("cost_name_weight.{name}"),
+                    format!("no critic named `{name}`"),
+                ));
+            }
+        }
+        Ok(())
     }
 
     /// Get candidate velocities from current velocity
     pub(crate) fn sample_velocity(&self, current_velocity: &Velocity) -> Vec<Velocity> {
+        match &self.motion_model {
+            MotionModel::Unicycle => self.sample_unicycle_velocity(current_velocity),
+            MotionModel::Ackermann(model) => model.sample_velocity(
+                current_velocity,
+                &self.limits,
+                self.controller_dt,
+                self.num_vel_sample,
+            ),
+        }
+    }
+
+    fn sample_unicycle_velocity(&self, current_velocity: &Velocity) -> Vec<Velocity> {
         let max_x_limit = (current_velocity.x + self.limits.max_accel.x * self.controller_dt)
             .clamp(self.limits.min_velocity.x, self.limits.max_velocity.x);
         let min_x_limit = (current_velocity.x + self.limits.min_accel.x * self.controller_dt)
@@ -173,30 +552,72 @@ impl DwaPlanner {
                 self.limits.min_velocity.theta,
                 self.limits.max_velocity.theta,
             );
+        // y is sampled only for the holonomic robots
+        let (min_y_limit, max_y_limit) = if self.is_holonomic() {
+            (
+                (current_velocity.y + self.limits.min_accel.y * self.controller_dt)
+                    .clamp(self.limits.min_velocity.y, self.limits.max_velocity.y),
+                (current_velocity.y + self.limits.max_accel.y * self.controller_dt)
+                    .clamp(self.limits.min_velocity.y, self.limits.max_velocity.y),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let num_y_sample = if self.is_holonomic() {
+            self.num_vel_sample
+        } else {
+            0
+        };
         let d_vel_x = (max_x_limit - min_x_limit) / self.num_vel_sample as f64;
+        let d_vel_y = if num_y_sample > 0 {
+            (max_y_limit - min_y_limit) / num_y_sample as f64
+        } else {
+            0.0
+        };
         let d_vel_theta = (max_theta_limit - min_theta_limit) / self.num_vel_sample as f64;
         let mut velocities = vec![];
         for i in 0..(self.num_vel_sample + 1) {
-            for j in 0..(self.num_vel_sample + 1) {
+            for k in 0..(num_y_sample + 1) {
+                let y = min_y_limit + d_vel_y * k as f64;
+                for j in 0..(self.num_vel_sample + 1) {
+                    velocities.push(Velocity {
+                        x: min_x_limit + d_vel_x * j as f64,
+                        y,
+                        theta: min_theta_limit + d_vel_theta * i as f64,
+                    });
+                }
                 velocities.push(Velocity {
-                    x: min_x_limit + d_vel_x * j as f64,
+                    x: 0.0,
+                    y,
                     theta: min_theta_limit + d_vel_theta * i as f64,
                 });
             }
-            velocities.push(Velocity {
-                x: 0.0,
-                theta: min_theta_limit + d_vel_theta * i as f64,
-            });
         }
         velocities
     }
 
-    fn forward_simulation(&self, current_pose: &Pose, target_velocity: &Velocity) -> Vec<Pose> {
+    /// Return true if the limits allow the y velocity
+    pub fn is_holonomic(&self) -> bool {
+        self.limits.max_velocity.y > self.limits.min_velocity.y
+    }
+
+    fn forward_simulation(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        target_velocity: &Velocity,
+    ) -> Vec<Pose> {
         let mut last_pose = current_pose.to_owned();
-        let diff = velocity_to_pose(target_velocity, self.controller_dt);
         let mut poses = vec![];
-        for _ in 0..(self.simulation_duration / self.controller_dt) as usize {
-            let next_pose = last_pose * diff;
+        for velocity in self.velocity_profile.velocities(
+            current_velocity,
+            target_velocity,
+            &self.limits,
+            self.controller_dt,
+            (self.simulation_duration / self.controller_dt) as usize,
+        ) {
+            let velocity = self.motion_model.feasible_velocity(&velocity);
+            let next_pose = last_pose * velocity_to_pose(&velocity, self.controller_dt);
             poses.push(next_pose);
             last_pose = next_pose;
         }
@@ -214,11 +635,128 @@ impl DwaPlanner {
             .map(|v| Plan {
                 velocity: v.to_owned(),
                 cost: 0.0,
-                path: self.forward_simulation(current_pose, &v),
+                path: self.forward_simulation(current_pose, current_velocity, &v),
+                cost_breakdown: HashMap::new(),
             })
             .collect::<Vec<_>>()
     }
 
+    /// Get predicted plan candidates with the cost of each critic
+    ///
+    /// Each weight in `cost_name_weight` is applied to the critic with the same name, and the
+    /// weights without the critic are skipped (see [`DwaPlanner::validate_critics`]). A plan
+    /// rejected by any critic costs `f64::MAX` regardless of the weights.
+    pub fn scored_plan_candidates(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<String, f64>,
+    ) -> Vec<Plan> {
+        self.scored_plan_candidates_with_obstacles(
+            current_pose,
+            current_velocity,
+            maps,
+            angles,
+            &[],
+        )
+    }
+
+    /// Get predicted plan candidates with the cost of each critic, considering the moving obstacles
+    pub fn scored_plan_candidates_with_obstacles(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<String, f64>,
+        dynamic_obstacles: &[MovingObstacle],
+    ) -> Vec<Plan> {
+        self.score_candidates(
+            current_pose,
+            current_velocity,
+            maps,
+            angles,
+            dynamic_obstacles,
+            cfg!(feature = "parallel"),
+        )
+        .into_plans()
+    }
+
+    /// Score the candidates without building the cost breakdowns
+    fn score_candidates(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<String, f64>,
+        dynamic_obstacles: &[MovingObstacle],
+        parallel: bool,
+    ) -> ScoredCandidates<'_> {
+        let context = CriticContext {
+            current_pose,
+            current_velocity,
+            maps,
+            angles,
+            limits: &self.limits,
+            cell_policies: &self.cell_policies,
+            dynamic_obstacles,
+            controller_dt: self.controller_dt,
+        };
+        let (names, weighted_critics): (Vec<_>, Vec<_>) = self
+            .cost_name_weight
+            .iter()
+            .filter_map(|(name, weight)| Some((name.as_str(), (*weight, self.critic(name)?))))
+            .unzip();
+        let mut plans = self.predicted_plan_candidates(current_pose, current_velocity);
+        // one row of the costs for each plan, which is never empty to be split into the rows
+        let row_len = weighted_critics.len().max(1);
+        let mut costs = vec![0.0; plans.len() * row_len];
+        let score = |(plan, costs): (&mut Plan, &mut [f64])| {
+            let mut all_layer_cost = 0.0;
+            let mut rejected = false;
+            for ((v, critic), weighted_cost) in weighted_critics.iter().zip(costs) {
+                let cost = critic.cost(plan, &context);
+                // checked before the weighting, which can make it finite
+                *weighted_cost = if cost == f64::MAX || !cost.is_finite() {
+                    rejected = true;
+                    f64::MAX
+                } else {
+                    v * cost
+                };
+                all_layer_cost += *weighted_cost;
+            }
+            plan.cost = if rejected { f64::MAX } else { all_layer_cost };
+        };
+        // The order of the plans is kept, so the selected plan is the same in both cases.
+        #[cfg(feature = "parallel")]
+        if parallel {
+            use rayon::prelude::*;
+            plans
+                .par_iter_mut()
+                .zip(costs.par_chunks_mut(row_len))
+                .for_each(score);
+        } else {
+            plans
+                .iter_mut()
+                .zip(costs.chunks_mut(row_len))
+                .for_each(score);
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            debug_assert!(!parallel, "the parallel feature is disabled");
+            plans
+                .iter_mut()
+                .zip(costs.chunks_mut(row_len))
+                .for_each(score);
+        }
+        ScoredCandidates {
+            names,
+            plans,
+            costs,
+            row_len,
+        }
+    }
+
     /// Plan the path using forward simulation
     pub fn plan_local_path(
         &self,
@@ -227,41 +765,60 @@ impl DwaPlanner {
         maps: &LayeredGridMap<u8>,
         angles: &HashMap<String, f64>,
     ) -> Plan {
-        let plans = self.predicted_plan_candidates(current_pose, current_velocity);
-        let mut min_cost = f64::MAX;
-        let mut selected_plan = Plan::default();
-        for plan in plans {
-            let mut all_layer_cost = 0.0;
-            for (cost_name, v) in &self.cost_name_weight {
-                let dist_cost = match maps.layer(cost_name) {
-                    Some(map) => {
-                        v * accumulate_values_by_positions(
-                            map,
-                            &plan
-                                .path
-                                .iter()
-                                .map(|p| Position::new(p.translation.x, p.translation.y))
-                                .collect::<Vec<_>>(),
-                        )
-                    }
-                    None => 0.,
-                };
-                all_layer_cost += dist_cost;
+        self.plan_local_path_with_obstacles(current_pose, current_velocity, maps, angles, &[])
+    }
 
-                let angle_cost = match angles.get(cost_name) {
-                    Some(angle) => v * (angle - plan.path.last().unwrap().rotation.angle()).abs(),
-                    None => 0.,
-                };
-                all_layer_cost += angle_cost;
-            }
+    /// Plan the path using forward simulation, avoiding the moving obstacles
+    ///
+    /// The obstacles are used by the critics such as [`DynamicObstacleCritic`].
+    pub fn plan_local_path_with_obstacles(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<String, f64>,
+        dynamic_obstacles: &[MovingObstacle],
+    ) -> Plan {
+        self.score_candidates(
+            current_pose,
+            current_velocity,
+            maps,
+            angles,
+            dynamic_obstacles,
+            cfg!(feature = "parallel"),
+        )
+        .into_min_cost_plan()
+    }
 
-            if all_layer_cost < min_cost {
-                min_cost = all_layer_cost;
-                selected_plan = plan.clone();
-            }
+    /// Register a critic. It overrides the critic with the same name in the config.
+    pub fn add_critic(&mut self, critic: impl TrajectoryCritic + 'static) {
+        self.custom_critics.0.push(Arc::new(critic));
+    }
+
+    /// Get the critic by name
+    pub fn critic(&self, name: &str) -> Option<&dyn TrajectoryCritic> {
+        if let Some(critic) = self
+            .custom_critics
+            .0
+            .iter()
+            .rev()
+            .find(|c| c.name() == name)
+        {
+            return Some(critic.as_ref());
         }
-        selected_plan.cost = min_cost;
-        selected_plan
+        self.critics
+            .iter()
+            .find(|c| c.name() == name)
+            .map(|c| c as &dyn TrajectoryCritic)
+    }
+
+    /// Critics configured in the config
+    pub fn critic_configs(&self) -> &[CriticConfig] {
+        &self.critics
+    }
+
+    pub fn critic_configs_mut(&mut self) -> &mut Vec<CriticConfig> {
+        &mut self.critics
     }
 
     pub fn limits(&self) -> &Limits {
@@ -291,6 +848,30 @@ impl DwaPlanner {
     pub fn num_vel_sample(&self) -> i32 {
         self.num_vel_sample
     }
+
+    pub fn motion_model(&self) -> &MotionModel {
+        &self.motion_model
+    }
+
+    pub fn set_motion_model(&mut self, motion_model: MotionModel) {
+        self.motion_model = motion_model;
+    }
+
+    pub fn velocity_profile(&self) -> &VelocityProfile {
+        &self.velocity_profile
+    }
+
+    pub fn set_velocity_profile(&mut self, velocity_profile: VelocityProfile) {
+        self.velocity_profile = velocity_profile;
+    }
+
+    pub fn cell_policies(&self) -> &CellPolicies {
+        &self.cell_policies
+    }
+
+    pub fn set_cell_policies(&mut self, cell_policies: CellPolicies) {
+        self.cell_policies = cell_policies;
+    }
 }
 
 #[cfg(test)]
@@ -322,6 +903,628 @@ mod tests {
     #[test]
     fn new_from_config_test() {
         let _ = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        assert!(matches!(
+            DwaPlanner::new_from_config("config/not_found.yaml"),
+            Err(crate::Error::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn to_config_text_test() {
+        let mut planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        planner.map_name_weight_mut().insert("path".to_owned(), 0.5);
+        planner.set_motion_model(MotionModel::Ackermann(AckermannModel {
+            wheel_base: 0.5,
+            min_turning_radius: 1.0,
+            max_steering_rate: 1.0,
+        }));
+        let text = planner.to_config_text().unwrap();
+        let loaded = DwaPlanner::new_from_config_text(&text).unwrap();
+        assert_eq!(loaded.map_name_weight(), planner.map_name_weight());
+        assert_eq!(loaded.motion_model(), planner.motion_model());
+        assert_eq!(loaded.cell_policies(), planner.cell_policies());
+        assert_eq!(loaded.critic_configs(), planner.critic_configs());
+        assert_eq!(loaded.to_config_text().unwrap(), text);
+
+        let path = std::env::temp_dir().join("openrr_nav_save_config_test.yaml");
+        planner.save_config(&path).unwrap();
+        let saved = DwaPlanner::new_from_config(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.to_config_text().unwrap(), text);
+    }
+
+    #[test]
+    fn validate_test() {
+        let config = |limits: &str, rest: &str| {
+            format!(
+                r#"
+DwaPlanner:
+  limits:
+{limits}
+  cost_name_weight: []
+{rest}
+"#
+            )
+        };
+        let limits = r#"
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [0.0, -2.0]
+    min_acceleration: [-2.0, -5.0]"#;
+        let rest = r#"
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 5"#;
+        let invalid_field = |text: String| match DwaPlanner::new_from_config_text(&text) {
+            Err(crate::Error::InvalidConfig { field, .. }) => field,
+            r => panic!("unexpected result: {r:?}"),
+        };
+        DwaPlanner::new_from_config_text(&config(limits, rest)).unwrap();
+        assert_eq!(
+            invalid_field(config(
+                limits,
+                &rest.replace("num_vel_sample: 5", "num_vel_sample: 0")
+            )),
+            "num_vel_sample"
+        );
+        assert_eq!(
+            invalid_field(config(
+                limits,
+                &rest.replace("controller_dt: 0.1", "controller_dt: 0.0")
+            )),
+            "controller_dt"
+        );
+        assert_eq!(
+            invalid_field(config(
+                limits,
+                &rest.replace("simulation_duration: 1.0", "simulation_duration: 0.05")
+            )),
+            "simulation_duration"
+        );
+        assert_eq!(
+            invalid_field(config(
+                &limits.replace("min_velocity: [0.0, -2.0]", "min_velocity: [0.0, 3.0]"),
+                rest
+            )),
+            "limits.min_velocity.theta"
+        );
+        assert_eq!(
+            invalid_field(config(
+                &limits.replace(
+                    "min_acceleration: [-2.0, -5.0]",
+                    "min_acceleration: [2.0, -5.0]"
+                ),
+                rest
+            )),
+            "limits.min_acceleration.x"
+        );
+        assert_eq!(
+            invalid_field(config(
+                limits,
+                &format!("{rest}\n  motion_model:\n    type: ackermann\n    wheel_base: 0.0\n    min_turning_radius: 1.0\n    max_steering_rate: 1.0")
+            )),
+            "motion_model.wheel_base"
+        );
+
+        assert_eq!(
+            invalid_field(config(limits, rest).replace(
+                "cost_name_weight: []",
+                "cost_name_weight:\n    - name: unknown\n      value: 1.0"
+            )),
+            "cost_name_weight.unknown"
+        );
+
+        let mut planner = DwaPlanner::new_from_config_text(&config(limits, rest)).unwrap();
+        planner.set_velocity_profile(VelocityProfile::RampThenStop {
+            hold_duration: -1.0,
+        });
+        assert!(planner.validate().is_err());
+    }
+
+    #[test]
+    fn cell_policies_test() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.35, 0.15), 0.1);
+        map.set_value(&Grid::new(0, 0), 1).unwrap();
+        map.cells_mut()[1] = Cell::Unknown;
+        let positions = [
+            Position::new(0.05, 0.05),
+            Position::new(0.15, 0.05),
+            Position::new(0.25, 0.05),
+        ];
+
+        let default_policies = CellPolicies::default();
+        assert_eq!(
+            accumulate_values_by_positions(&map, positions.iter().copied(), &default_policies),
+            f64::MAX
+        );
+        let policies = CellPolicies {
+            unknown: CellPolicy::Ignore,
+            uninitialized: CellPolicy::Cost(10.0),
+        };
+        assert_eq!(
+            accumulate_values_by_positions(&map, positions.iter().copied(), &policies),
+            11.0
+        );
+        let policies = CellPolicies {
+            unknown: CellPolicy::Lethal,
+            uninitialized: CellPolicy::Ignore,
+        };
+        assert_eq!(
+            accumulate_values_by_positions(&map, positions.iter().copied(), &policies),
+            f64::MAX
+        );
+        let policies = CellPolicies {
+            unknown: CellPolicy::Cost(255.0),
+            uninitialized: CellPolicy::Ignore,
+        };
+        assert_eq!(
+            accumulate_values_by_positions(&map, positions.iter().copied(), &policies),
+            256.0
+        );
+    }
+
+    #[test]
+    fn cell_policies_config_test() {
+        let planner = DwaPlanner::new_from_config_text(
+            r#"
+DwaPlanner:
+  limits:
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [0.0, -2.0]
+    min_acceleration: [-2.0, -5.0]
+  cost_name_weight: []
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 5
+  cell_policies:
+    unknown: !cost 100.0
+    uninitialized: ignore
+"#,
+        )
+        .unwrap();
+        assert_eq!(planner.cell_policies().unknown, CellPolicy::Cost(100.0));
+        assert_eq!(planner.cell_policies().uninitialized, CellPolicy::Ignore);
+    }
+
+    #[test]
+    fn motion_model_config_test() {
+        let planner = DwaPlanner::new_from_config_text(
+            r#"
+DwaPlanner:
+  limits:
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [0.0, -2.0]
+    min_acceleration: [-2.0, -5.0]
+  cost_name_weight: []
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 5
+  motion_model:
+    type: ackermann
+    wheel_base: 0.5
+    min_turning_radius: 1.0
+    max_steering_rate: 0.5
+"#,
+        )
+        .unwrap();
+        let MotionModel::Ackermann(model) = planner.motion_model().clone() else {
+            panic!("unexpected motion model");
+        };
+        assert_eq!(model.wheel_base, 0.5);
+        let velocities = planner.sample_velocity(&Velocity::default());
+        assert_eq!(velocities.len(), 36);
+        for v in velocities {
+            assert!(v.theta.abs() <= v.x.abs() / model.min_turning_radius + 1e-9);
+        }
+    }
+
+    #[test]
+    fn ramp_forward_simulation_test() {
+        let mut planner = DwaPlanner::new(
+            Limits {
+                max_velocity: [1.0, 1.0].into(),
+                max_accel: [0.5, 1.0].into(),
+                min_velocity: [0.0, -1.0].into(),
+                min_accel: [-0.5, -1.0].into(),
+            },
+            HashMap::new(),
+            0.1,
+            1.0,
+            2,
+        );
+        let target = [1.0, 0.0].into();
+        let poses = planner.forward_simulation(&Pose::identity(), &Velocity::default(), &target);
+        assert_eq!(poses.len(), 10);
+        assert!((poses.last().unwrap().translation.x - 1.0).abs() < 1e-9);
+
+        planner.set_velocity_profile(VelocityProfile::Ramp);
+        let poses = planner.forward_simulation(&Pose::identity(), &Velocity::default(), &target);
+        assert_eq!(poses.len(), 10);
+        // v = 0.05 * i for i = 1..=10
+        assert!((poses.last().unwrap().translation.x - 0.275).abs() < 1e-9);
+
+        planner.set_velocity_profile(VelocityProfile::RampThenStop { hold_duration: 0.0 });
+        let poses = planner.forward_simulation(&Pose::identity(), &[1.0, 0.0].into(), &target);
+        // slows down after reaching the target: v = 1.0, 0.95, ..., 0.55
+        assert!((poses.last().unwrap().translation.x - 0.775).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ackermann_ramp_forward_simulation_test() {
+        let model = AckermannModel {
+            wheel_base: 0.5,
+            min_turning_radius: 1.0,
+            max_steering_rate: 1.0,
+        };
+        let mut planner = DwaPlanner::new(
+            Limits {
+                max_velocity: [1.0, 2.0].into(),
+                max_accel: [0.5, 5.0].into(),
+                min_velocity: [0.0, -2.0].into(),
+                min_accel: [-0.5, -5.0].into(),
+            },
+            HashMap::new(),
+            0.1,
+            1.0,
+            2,
+        );
+        planner.set_motion_model(MotionModel::Ackermann(model.clone()));
+        planner.set_velocity_profile(VelocityProfile::Ramp);
+        // the turning velocity ramps faster than the speed
+        let target = model.velocity(1.0, model.max_steering_angle());
+        let poses = planner.forward_simulation(&Pose::identity(), &Velocity::default(), &target);
+        let mut last_pose = Pose::identity();
+        for pose in poses {
+            let angle = (pose.rotation.angle() - last_pose.rotation.angle()).abs();
+            let chord = (pose.translation.vector - last_pose.translation.vector).norm();
+            // radius of the arc through the poses
+            assert!(chord >= 2.0 * model.min_turning_radius * (angle / 2.0).sin() - 1e-9);
+            last_pose = pose;
+        }
+    }
+
+    #[test]
+    fn dynamic_obstacle_test() {
+        // the predicted collisions are rejected even with the small weights
+        for weight in [1.0, 0.5, 0.0] {
+            let mut weights = HashMap::new();
+            weights.insert("velocity".to_owned(), 1.0);
+            weights.insert("dynamic_obstacle".to_owned(), weight);
+            let mut planner = DwaPlanner::new(
+                Limits {
+                    max_velocity: [0.5, 1.0].into(),
+                    max_accel: [5.0, 1.0].into(),
+                    min_velocity: [0.0, -1.0].into(),
+                    min_accel: [-5.0, -1.0].into(),
+                },
+                weights,
+                0.1,
+                1.0,
+                5,
+            );
+            planner.add_critic(VelocityCritic {
+                name: "velocity".to_owned(),
+            });
+            planner.add_critic(DynamicObstacleCritic {
+                name: "dynamic_obstacle".to_owned(),
+                robot_radius: 0.1,
+                influence_distance: 0.0,
+            });
+            let maps = LayeredGridMap::default();
+            let angles = HashMap::new();
+            let current_velocity = [0.5, 0.0].into();
+            let plan =
+                planner.plan_local_path(&Pose::identity(), &current_velocity, &maps, &angles);
+            assert_eq!(plan.velocity.x, 0.5);
+
+            // crosses in front of the robot at x = 0.5 after 1 second
+            let obstacles = [MovingObstacle::new(
+                Vector2::new(0.5, 0.5),
+                Vector2::new(0.0, -0.5),
+                0.1,
+            )];
+            let plan = planner.plan_local_path_with_obstacles(
+                &Pose::identity(),
+                &current_velocity,
+                &maps,
+                &angles,
+                &obstacles,
+            );
+            assert!(plan.velocity.x < 0.5, "weight {weight}");
+            assert_eq!(plan.cost_breakdown["dynamic_obstacle"], 0.0);
+            let candidates = planner.scored_plan_candidates_with_obstacles(
+                &Pose::identity(),
+                &current_velocity,
+                &maps,
+                &angles,
+                &obstacles,
+            );
+            let rejected = candidates
+                .iter()
+                .filter(|c| c.cost_breakdown["dynamic_obstacle"] == f64::MAX)
+                .collect::<Vec<_>>();
+            assert!(!rejected.is_empty());
+            assert!(
+                rejected.iter().all(|c| c.cost == f64::MAX),
+                "weight {weight}"
+            );
+        }
+    }
+
+    #[derive(Debug)]
+    struct BackwardCritic;
+
+    impl TrajectoryCritic for BackwardCritic {
+        fn name(&self) -> &str {
+            "backward"
+        }
+
+        fn cost(&self, plan: &Plan, _context: &CriticContext<'_>) -> f64 {
+            plan.velocity.x
+        }
+    }
+
+    #[test]
+    fn custom_critic_test() {
+        let mut weights = HashMap::new();
+        weights.insert("backward".to_owned(), 1.0);
+        weights.insert("twirling".to_owned(), 1.0);
+        let mut planner = DwaPlanner::new(
+            Limits {
+                max_velocity: Velocity {
+                    x: 0.5,
+                    y: 0.0,
+                    theta: 2.0,
+                },
+                max_accel: Acceleration {
+                    x: 2.0,
+                    y: 0.0,
+                    theta: 5.0,
+                },
+                min_velocity: Velocity {
+                    x: -0.5,
+                    y: 0.0,
+                    theta: -2.0,
+                },
+                min_accel: Acceleration {
+                    x: -2.0,
+                    y: 0.0,
+                    theta: -5.0,
+                },
+            },
+            weights,
+            0.1,
+            1.0,
+            4,
+        );
+        planner
+            .critic_configs_mut()
+            .push(CriticConfig::Twirling(TwirlingCritic {
+                name: "twirling".to_owned(),
+            }));
+        planner.add_critic(BackwardCritic);
+        assert!(planner.critic("backward").is_some());
+        assert!(planner.critic("twirling").is_some());
+        assert!(planner.critic("unknown").is_none());
+
+        let plan = planner.plan_local_path(
+            &Pose::identity(),
+            &Velocity::default(),
+            &LayeredGridMap::default(),
+            &HashMap::new(),
+        );
+        assert!((plan.velocity.x + 0.2).abs() < 1e-9);
+        assert!(plan.velocity.theta.abs() < 1e-9);
+    }
+
+    #[test]
+    fn custom_critic_config_test() {
+        let source = r#"
+DwaPlanner:
+  limits:
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [-0.5, -2.0]
+    min_acceleration: [-2.0, -5.0]
+  cost_name_weight:
+    - name: backward
+      value: 1.0
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 4
+"#;
+        assert!(matches!(
+            DwaPlanner::new_from_config_text(source),
+            Err(crate::Error::InvalidConfig { field, .. }) if field == "cost_name_weight.backward"
+        ));
+        let planner =
+            DwaPlanner::new_from_config_text_with_critics(source, [Arc::new(BackwardCritic) as _])
+                .unwrap();
+        assert_eq!(planner.map_name_weight()["backward"], 1.0);
+        let plan = planner.plan_local_path(
+            &Pose::identity(),
+            &Velocity::default(),
+            &LayeredGridMap::default(),
+            &HashMap::new(),
+        );
+        assert!((plan.velocity.x + 0.2).abs() < 1e-9);
+
+        // registered after loading
+        let mut planner: DwaPlannerConfig = serde_yaml::from_str(source).unwrap();
+        planner.dwa_planner.validate().unwrap();
+        assert!(planner.dwa_planner.validate_critics().is_err());
+        planner.dwa_planner.add_critic(BackwardCritic);
+        planner.dwa_planner.validate_critics().unwrap();
+    }
+
+    #[derive(Debug)]
+    struct LethalCritic {
+        /// rejects the plans faster than this
+        max_velocity: f64,
+    }
+
+    impl TrajectoryCritic for LethalCritic {
+        fn name(&self) -> &str {
+            "lethal"
+        }
+
+        fn cost(&self, plan: &Plan, _context: &CriticContext<'_>) -> f64 {
+            if plan.velocity.x > self.max_velocity {
+                f64::MAX
+            } else {
+                0.0
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_plan_test() {
+        for weight in [0.5, 0.0] {
+            let mut weights = HashMap::new();
+            weights.insert("lethal".to_owned(), weight);
+            weights.insert("velocity".to_owned(), 1.0);
+            let mut planner = DwaPlanner::new(
+                Limits {
+                    max_velocity: [0.5, 2.0].into(),
+                    max_accel: [2.0, 5.0].into(),
+                    min_velocity: [0.0, -2.0].into(),
+                    min_accel: [-2.0, -5.0].into(),
+                },
+                weights,
+                0.1,
+                1.0,
+                4,
+            );
+            planner.add_critic(LethalCritic { max_velocity: 0.1 });
+            let maps = LayeredGridMap::default();
+            let angles = HashMap::new();
+            let candidates = planner.scored_plan_candidates(
+                &Pose::identity(),
+                &Velocity::default(),
+                &maps,
+                &angles,
+            );
+            for candidate in &candidates {
+                if candidate.velocity.x > 0.1 {
+                    assert_eq!(candidate.cost, f64::MAX, "weight {weight}");
+                    assert_eq!(candidate.cost_breakdown["lethal"], f64::MAX);
+                } else {
+                    assert!(candidate.cost < f64::MAX, "weight {weight}");
+                }
+            }
+            // the fastest plan which is not rejected
+            let plan =
+                planner.plan_local_path(&Pose::identity(), &Velocity::default(), &maps, &angles);
+            assert!((plan.velocity.x - 0.1).abs() < 1e-9, "weight {weight}");
+
+            // all rejected
+            planner.add_critic(LethalCritic { max_velocity: -1.0 });
+            let plan =
+                planner.plan_local_path(&Pose::identity(), &Velocity::default(), &maps, &angles);
+            assert_eq!(plan.cost, f64::MAX, "weight {weight}");
+            assert!(plan.path.is_empty());
+        }
+    }
+
+    #[test]
+    fn cost_breakdown_test() {
+        let mut weights = HashMap::new();
+        weights.insert("twirling".to_owned(), 0.5);
+        weights.insert("velocity".to_owned(), 2.0);
+        let mut planner = DwaPlanner::new(
+            Limits {
+                max_velocity: Velocity {
+                    x: 0.5,
+                    y: 0.0,
+                    theta: 2.0,
+                },
+                max_accel: Acceleration {
+                    x: 2.0,
+                    y: 0.0,
+                    theta: 5.0,
+                },
+                min_velocity: Velocity {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: -2.0,
+                },
+                min_accel: Acceleration {
+                    x: -2.0,
+                    y: 0.0,
+                    theta: -5.0,
+                },
+            },
+            weights,
+            0.1,
+            1.0,
+            4,
+        );
+        planner
+            .critic_configs_mut()
+            .push(CriticConfig::Twirling(TwirlingCritic {
+                name: "twirling".to_owned(),
+            }));
+        planner
+            .critic_configs_mut()
+            .push(CriticConfig::Velocity(VelocityCritic {
+                name: "velocity".to_owned(),
+            }));
+        let maps = LayeredGridMap::default();
+        let angles = HashMap::new();
+        let candidates =
+            planner.scored_plan_candidates(&Pose::identity(), &Velocity::default(), &maps, &angles);
+        assert_eq!(candidates.len(), 30);
+        for candidate in &candidates {
+            assert_eq!(candidate.cost_breakdown.len(), 2);
+            assert!(
+                (candidate.cost_breakdown["twirling"] - 0.5 * candidate.velocity.theta.abs()).abs()
+                    < 1e-9
+            );
+            let sum: f64 = candidate.cost_breakdown.values().sum();
+            assert!((candidate.cost - sum).abs() < 1e-9);
+        }
+        let plan = planner.plan_local_path(&Pose::identity(), &Velocity::default(), &maps, &angles);
+        assert!(candidates.iter().all(|c| c.cost >= plan.cost));
+        assert!((plan.velocity.x - 0.2).abs() < 1e-9);
+        assert!(plan.velocity.theta.abs() < 1e-9);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_scoring_test() {
+        let map = new_sample_map();
+        let start = map.to_grid(-0.8, -0.9).unwrap();
+        let goal = map.to_grid(2.5, 0.5).unwrap();
+        let path_grid = (start.x..=goal.x)
+            .map(|x| Grid::new(x, start.y))
+            .collect::<Vec<_>>();
+        let mut maps = HashMap::new();
+        maps.insert(
+            "path".to_owned(),
+            path_distance_map(&map, &path_grid).unwrap(),
+        );
+        maps.insert("goal".to_owned(), goal_distance_map(&map, &goal).unwrap());
+        maps.insert("obstacle".to_owned(), obstacle_distance_map(&map).unwrap());
+        let maps = LayeredGridMap::new(maps);
+        let mut angles = HashMap::new();
+        angles.insert("path_direction".to_owned(), 0.3);
+        let planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        let current_velocity = [0.2, 0.5].into();
+        for (x, y) in [(-0.8, -0.9), (0.0, 0.0), (2.0, 0.5)] {
+            let pose = Pose::new(Vector2::new(x, y), 0.5);
+            let score = |parallel| {
+                planner.score_candidates(&pose, &current_velocity, &maps, &angles, &[], parallel)
+            };
+            let (parallel, serial) = (score(true), score(false));
+            assert_eq!(parallel.costs, serial.costs);
+            let costs = |plans: &[Plan]| plans.iter().map(|p| p.cost).collect::<Vec<_>>();
+            assert_eq!(costs(&parallel.plans), costs(&serial.plans));
+            let (parallel, serial) = (parallel.into_min_cost_plan(), serial.into_min_cost_plan());
+            assert_eq!(parallel.velocity, serial.velocity);
+            assert_eq!(parallel.cost_breakdown, serial.cost_breakdown);
+        }
     }
 
     #[test]
@@ -385,14 +1588,24 @@ mod tests {
 
         let planner = DwaPlanner::new(
             Limits {
-                max_velocity: Velocity { x: 0.5, theta: 2.0 },
-                max_accel: Acceleration { x: 2.0, theta: 5.0 },
+                max_velocity: Velocity {
+                    x: 0.5,
+                    y: 0.0,
+                    theta: 2.0,
+                },
+                max_accel: Acceleration {
+                    x: 2.0,
+                    y: 0.0,
+                    theta: 5.0,
+                },
                 min_velocity: Velocity {
                     x: 0.0,
+                    y: 0.0,
                     theta: -2.0,
                 },
                 min_accel: Acceleration {
                     x: -2.0,
+                    y: 0.0,
                     theta: -5.0,
                 },
             },
@@ -404,7 +1617,7 @@ mod tests {
 
         let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), 0.0);
         let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), 0.0);
-        let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
+        let mut current_velocity = Velocity::default();
         let mut plan_map = map.clone();
         let mut reached = false;
         for i in 0..100 {
@@ -438,18 +1651,87 @@ mod tests {
         assert!(reached);
     }
 
+    #[test]
+    fn velocity_config_test() {
+        let velocity: Velocity = serde_yaml::from_str("[0.5, 2.0]").unwrap();
+        assert_eq!((velocity.x, velocity.y, velocity.theta), (0.5, 0.0, 2.0));
+        let velocity: Velocity = serde_yaml::from_str("[0.5, 0.3, 2.0]").unwrap();
+        assert_eq!((velocity.x, velocity.y, velocity.theta), (0.5, 0.3, 2.0));
+        assert!(serde_yaml::from_str::<Velocity>("[0.5]").is_err());
+        assert!(serde_yaml::from_str::<Acceleration>("[0.5, 0.1, 0.2, 0.3]").is_err());
+        assert_eq!(
+            serde_yaml::to_string(&Velocity::from([0.5, 2.0])).unwrap(),
+            serde_yaml::to_string(&[0.5, 2.0]).unwrap()
+        );
+        assert_eq!(
+            serde_yaml::to_string(&Acceleration::from([0.5, 0.3, 2.0])).unwrap(),
+            serde_yaml::to_string(&[0.5, 0.3, 2.0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn holonomic_sample_velocities_test() {
+        let planner = DwaPlanner::new(
+            Limits {
+                max_velocity: [0.5, 0.3, 1.0].into(),
+                max_accel: [1.0, 1.0, 2.0].into(),
+                min_velocity: [-0.5, -0.3, -1.0].into(),
+                min_accel: [-1.0, -1.0, -2.0].into(),
+            },
+            HashMap::new(),
+            0.1,
+            1.0,
+            2,
+        );
+        assert!(planner.is_holonomic());
+        let velocities = planner.sample_velocity(&Velocity::default());
+        assert_eq!(velocities.len(), 3 * 3 * 4);
+        assert!(velocities.iter().any(|v| (v.y - 0.1).abs() < 1e-9));
+        assert!(velocities.iter().any(|v| (v.y + 0.1).abs() < 1e-9));
+        assert!(velocities.iter().all(|v| v.y.abs() <= 0.1 + 1e-9));
+
+        // moves sideways
+        let poses = planner.forward_simulation(
+            &Pose::identity(),
+            &[0.0, 0.1, 0.0].into(),
+            &[0.0, 0.1, 0.0].into(),
+        );
+        let last = poses.last().unwrap();
+        assert!(last.translation.x.abs() < 1e-9);
+        assert!((last.translation.y - 0.1).abs() < 1e-9);
+
+        // differential drive never samples y
+        let mut planner = planner;
+        planner.limits.max_velocity.y = 0.0;
+        planner.limits.min_velocity.y = 0.0;
+        assert!(!planner.is_holonomic());
+        let velocities = planner.sample_velocity(&Velocity::default());
+        assert_eq!(velocities.len(), 3 * 4);
+        assert!(velocities.iter().all(|v| v.y == 0.0));
+    }
+
     #[test]
     fn test_sample_velocities() {
         let planner = DwaPlanner::new(
             Limits {
-                max_velocity: Velocity { x: 0.1, theta: 0.5 },
-                max_accel: Acceleration { x: 0.5, theta: 1.0 },
+                max_velocity: Velocity {
+                    x: 0.1,
+                    y: 0.0,
+                    theta: 0.5,
+                },
+                max_accel: Acceleration {
+                    x: 0.5,
+                    y: 0.0,
+                    theta: 1.0,
+                },
                 min_velocity: Velocity {
                     x: 0.0,
+                    y: 0.0,
                     theta: -0.5,
                 },
                 min_accel: Acceleration {
                     x: -0.5,
+                    y: 0.0,
                     theta: -1.0,
                 },
             },
@@ -458,14 +1740,16 @@ mod tests {
             3.0,
             5,
         );
-        let velocities = planner.sample_velocity(&Velocity { x: 0.0, theta: 0.0 });
+        let velocities = planner.sample_velocity(&Velocity::default());
         for velocity in velocities {
             println!("{velocity:?}");
         }
         let poses = planner.forward_simulation(
             &Pose::identity(),
+            &Velocity::default(),
             &Velocity {
                 x: 0.01,
+                y: 0.0,
                 theta: 0.1,
             },
         );
@@ -0,0 +1,507 @@
+use grid_map::{LayeredGridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use super::{accumulate_values_by_positions, CellPolicies, Limits, Plan, Pose, Velocity};
+use crate::{utils::normalize_angle, MovingObstacle};
+
+/// Inputs shared by all the critics in one planning cycle
+#[derive(Debug, Clone, Copy)]
+pub struct CriticContext<'a> {
+    pub current_pose: &'a Pose,
+    pub current_velocity: &'a Velocity,
+    pub maps: &'a LayeredGridMap<u8>,
+    pub angles: &'a HashMap<String, f64>,
+    pub limits: &'a Limits,
+    pub cell_policies: &'a CellPolicies,
+    /// Tracked moving obstacles at the time of planning
+    pub dynamic_obstacles: &'a [MovingObstacle],
+    /// Time step between the poses of the plan [s]
+    pub controller_dt: f64,
+}
+
+/// Scores a predicted trajectory for the DWA planner
+///
+/// The planner multiplies the cost by the weight registered with the same name
+/// in `cost_name_weight`, and critics without a weight are not evaluated.
+/// Every weight must have a critic, which is checked by
+/// [`DwaPlanner::validate_critics`](crate::DwaPlanner::validate_critics).
+pub trait TrajectoryCritic: Debug + Send + Sync {
+    /// Name of the critic, which is also the key of the weight
+    fn name(&self) -> &str;
+    /// Unweighted cost of the plan. `f64::MAX` rejects the plan regardless of the weight.
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64;
+}
+
+/// Sum of the values of the layer on the poses of the plan
+fn layer_cost(layer: &str, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+    match context.maps.layer(layer) {
+        Some(map) => accumulate_values_by_positions(
+            map,
+            plan.path
+                .iter()
+                .map(|p| Position::new(p.translation.x, p.translation.y)),
+            context.cell_policies,
+        ),
+        None => 0.,
+    }
+}
+
+/// Difference between the angle in the table and the heading of the plan, wrapped into [0, pi]
+///
+/// The heading is sampled at `num_samples` poses evenly spaced along the plan and the
+/// differences are averaged. Only the last pose is used if it is `None`.
+fn angle_cost(
+    angle: &str,
+    num_samples: Option<usize>,
+    plan: &Plan,
+    context: &CriticContext<'_>,
+) -> f64 {
+    let Some(angle) = context.angles.get(angle) else {
+        return 0.;
+    };
+    let len = plan.path.len();
+    if len == 0 {
+        return 0.;
+    }
+    let num_samples = num_samples.unwrap_or(1).clamp(1, len);
+    let sum = (1..=num_samples)
+        .map(|i| {
+            let pose = &plan.path[i * len / num_samples - 1];
+            normalize_angle(angle - pose.rotation.angle()).abs()
+        })
+        .sum::<f64>();
+    sum / num_samples as f64
+}
+
+/// Sum of the values of the layer on the poses, such as the path, goal or obstacle distance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerCritic {
+    pub name: String,
+    /// name of the layer (same as `name` if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+}
+
+impl TrajectoryCritic for LayerCritic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+        layer_cost(self.layer.as_deref().unwrap_or(&self.name), plan, context)
+    }
+}
+
+/// Difference between the heading of the plan and the angle in the angle table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeadingAlignmentCritic {
+    pub name: String,
+    /// name of the angle in the angle table (same as `name` if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub angle: Option<String>,
+    /// number of poses sampled along the plan (only the last pose if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub look_ahead_samples: Option<usize>,
+}
+
+impl TrajectoryCritic for HeadingAlignmentCritic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+        angle_cost(
+            self.angle.as_deref().unwrap_or(&self.name),
+            self.look_ahead_samples,
+            plan,
+            context,
+        )
+    }
+}
+
+/// Prefers the faster forward velocity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VelocityCritic {
+    pub name: String,
+}
+
+impl TrajectoryCritic for VelocityCritic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+        (context.limits.max_velocity.x - plan.velocity.x).max(0.)
+    }
+}
+
+/// Penalizes the rotational velocity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TwirlingCritic {
+    pub name: String,
+}
+
+impl TrajectoryCritic for TwirlingCritic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cost(&self, plan: &Plan, _context: &CriticContext<'_>) -> f64 {
+        plan.velocity.theta.abs()
+    }
+}
+
+/// Closeness to the moving obstacles at the same time step of the plan
+///
+/// The obstacles are moved with their constant velocities, and the plan is rejected
+/// if the robot overlaps any of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DynamicObstacleCritic {
+    pub name: String,
+    /// radius of the circle which covers the robot [m]
+    pub robot_radius: f64,
+    /// clearance below which the cost is added [m]
+    pub influence_distance: f64,
+}
+
+impl TrajectoryCritic for DynamicObstacleCritic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+        let mut cost = 0.;
+        for (i, pose) in plan.path.iter().enumerate() {
+            let time = (i + 1) as f64 * context.controller_dt;
+            let point = na::Vector2::new(pose.translation.x, pose.translation.y);
+            for obstacle in context.dynamic_obstacles {
+                let clearance = obstacle.clearance(&point, time) - self.robot_radius;
+                if clearance <= 0. {
+                    return f64::MAX;
+                }
+                if clearance < self.influence_distance {
+                    cost += 1. - clearance / self.influence_distance;
+                }
+            }
+        }
+        cost
+    }
+}
+
+/// Built-in critics which can be configured from the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CriticConfig {
+    Layer(LayerCritic),
+    HeadingAlignment(HeadingAlignmentCritic),
+    Velocity(VelocityCritic),
+    Twirling(TwirlingCritic),
+    DynamicObstacle(DynamicObstacleCritic),
+}
+
+impl CriticConfig {
+    /// Critics of the layers and the angles provided by the navigation, and the velocities
+    pub fn default_critics() -> Vec<Self> {
+        let layer = |name: &str| {
+            Self::Layer(LayerCritic {
+                name: name.to_owned(),
+                layer: None,
+            })
+        };
+        let heading = |name: &str| {
+            Self::HeadingAlignment(HeadingAlignmentCritic {
+                name: name.to_owned(),
+                angle: None,
+                look_ahead_samples: None,
+            })
+        };
+        vec![
+            layer("path"),
+            layer("goal"),
+            layer("obstacle"),
+            layer("local_goal"),
+            heading("rotation"),
+            heading("path_direction"),
+            heading("goal_direction"),
+            Self::Velocity(VelocityCritic {
+                name: "velocity".to_owned(),
+            }),
+            Self::Twirling(TwirlingCritic {
+                name: "twirling".to_owned(),
+            }),
+        ]
+    }
+
+    fn critic(&self) -> &dyn TrajectoryCritic {
+        match self {
+            Self::Layer(c) => c,
+            Self::HeadingAlignment(c) => c,
+            Self::Velocity(c) => c,
+            Self::Twirling(c) => c,
+            Self::DynamicObstacle(c) => c,
+        }
+    }
+}
+
+impl TrajectoryCritic for CriticConfig {
+    fn name(&self) -> &str {
+        self.critic().name()
+    }
+
+    fn cost(&self, plan: &Plan, context: &CriticContext<'_>) -> f64 {
+        self.critic().cost(plan, context)
+    }
+}
+
+/// Critics registered by the user, which are not serialized
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CustomCritics(pub(crate) Vec<Arc<dyn TrajectoryCritic>>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Acceleration;
+    use nalgebra::Vector2;
+
+    fn plan_with_heading(velocity: Velocity, heading: f64) -> Plan {
+        Plan {
+            velocity,
+            cost: 0.0,
+            path: vec![Pose::new(Vector2::new(0.0, 0.0), heading)],
+            cost_breakdown: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn built_in_critics_test() {
+        let limits = Limits {
+            max_velocity: Velocity {
+                x: 0.5,
+                y: 0.0,
+                theta: 2.0,
+            },
+            max_accel: Acceleration {
+                x: 2.0,
+                y: 0.0,
+                theta: 5.0,
+            },
+            min_velocity: Velocity {
+                x: 0.0,
+                y: 0.0,
+                theta: -2.0,
+            },
+            min_accel: Acceleration {
+                x: -2.0,
+                y: 0.0,
+                theta: -5.0,
+            },
+        };
+        let mut angles = HashMap::new();
+        angles.insert("path_direction".to_owned(), 1.0);
+        let maps = LayeredGridMap::default();
+        let context = CriticContext {
+            current_pose: &Pose::identity(),
+            current_velocity: &Velocity::default(),
+            maps: &maps,
+            angles: &angles,
+            limits: &limits,
+            cell_policies: &CellPolicies::default(),
+            dynamic_obstacles: &[],
+            controller_dt: 0.1,
+        };
+        let plan = plan_with_heading(
+            Velocity {
+                x: 0.2,
+                y: 0.0,
+                theta: -0.5,
+            },
+            0.25,
+        );
+
+        let heading = HeadingAlignmentCritic {
+            name: "path_direction".to_owned(),
+            angle: None,
+            look_ahead_samples: None,
+        };
+        assert_eq!(heading.cost(&plan, &context), 0.75);
+        let velocity = VelocityCritic {
+            name: "velocity".to_owned(),
+        };
+        assert!((velocity.cost(&plan, &context) - 0.3).abs() < 1e-9);
+        let twirling = TwirlingCritic {
+            name: "twirling".to_owned(),
+        };
+        assert_eq!(twirling.cost(&plan, &context), 0.5);
+        // missing layer costs nothing
+        let path = LayerCritic {
+            name: "path".to_owned(),
+            layer: None,
+        };
+        assert_eq!(path.cost(&plan, &context), 0.0);
+    }
+
+    #[test]
+    fn heading_alignment_wrap_test() {
+        let limits = Limits::default();
+        let maps = LayeredGridMap::default();
+        let mut angles = HashMap::new();
+        angles.insert("goal_direction".to_owned(), 3.1);
+        let context = CriticContext {
+            current_pose: &Pose::identity(),
+            current_velocity: &Velocity::default(),
+            maps: &maps,
+            angles: &angles,
+            limits: &limits,
+            cell_policies: &CellPolicies::default(),
+            dynamic_obstacles: &[],
+            controller_dt: 0.1,
+        };
+        let critic = HeadingAlignmentCritic {
+            name: "goal_direction".to_owned(),
+            angle: None,
+            look_ahead_samples: None,
+        };
+        // across the discontinuity at +/-pi
+        let plan = plan_with_heading(Velocity::default(), -3.1);
+        let expected = 2.0 * std::f64::consts::PI - 6.2;
+        assert!((critic.cost(&plan, &context) - expected).abs() < 1e-9);
+        let plan = plan_with_heading(Velocity::default(), 3.1);
+        assert!(critic.cost(&plan, &context).abs() < 1e-9);
+        // the opposite direction is the worst
+        let plan = plan_with_heading(Velocity::default(), 3.1 - std::f64::consts::PI);
+        assert!((critic.cost(&plan, &context) - std::f64::consts::PI).abs() < 1e-9);
+
+        let mut boundary_angles = HashMap::new();
+        boundary_angles.insert("goal_direction".to_owned(), -std::f64::consts::PI);
+        let context = CriticContext {
+            angles: &boundary_angles,
+            ..context
+        };
+        let plan = plan_with_heading(Velocity::default(), std::f64::consts::PI);
+        assert!(critic.cost(&plan, &context).abs() < 1e-9);
+    }
+
+    #[test]
+    fn heading_alignment_look_ahead_test() {
+        let limits = Limits::default();
+        let maps = LayeredGridMap::default();
+        let mut angles = HashMap::new();
+        angles.insert("path_direction".to_owned(), 3.0);
+        let context = CriticContext {
+            current_pose: &Pose::identity(),
+            current_velocity: &Velocity::default(),
+            maps: &maps,
+            angles: &angles,
+            limits: &limits,
+            cell_policies: &CellPolicies::default(),
+            dynamic_obstacles: &[],
+            controller_dt: 0.1,
+        };
+        let plan = Plan {
+            path: [2.0, 2.5, -3.0, 3.0]
+                .iter()
+                .map(|heading| Pose::new(Vector2::new(0.0, 0.0), *heading))
+                .collect(),
+            ..Default::default()
+        };
+        let mut critic = HeadingAlignmentCritic {
+            name: "path_direction".to_owned(),
+            angle: None,
+            look_ahead_samples: None,
+        };
+        assert!(critic.cost(&plan, &context).abs() < 1e-9);
+        critic.look_ahead_samples = Some(2);
+        // samples the 2nd and the 4th poses
+        assert!((critic.cost(&plan, &context) - 0.25).abs() < 1e-9);
+        critic.look_ahead_samples = Some(4);
+        let expected = (1.0 + 0.5 + (2.0 * std::f64::consts::PI - 6.0) + 0.0) / 4.0;
+        assert!((critic.cost(&plan, &context) - expected).abs() < 1e-9);
+        // more samples than poses uses all the poses
+        critic.look_ahead_samples = Some(10);
+        assert!((critic.cost(&plan, &context) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dynamic_obstacle_critic_test() {
+        let limits = Limits::default();
+        let maps = LayeredGridMap::default();
+        let angles = HashMap::new();
+        // crossing the path of the robot from the left
+        let obstacles = [MovingObstacle::new(
+            Vector2::new(0.5, 0.5),
+            Vector2::new(0.0, -1.0),
+            0.1,
+        )];
+        let context = CriticContext {
+            current_pose: &Pose::identity(),
+            current_velocity: &Velocity::default(),
+            maps: &maps,
+            angles: &angles,
+            limits: &limits,
+            cell_policies: &CellPolicies::default(),
+            dynamic_obstacles: &obstacles,
+            controller_dt: 0.1,
+        };
+        let critic = DynamicObstacleCritic {
+            name: "dynamic_obstacle".to_owned(),
+            robot_radius: 0.2,
+            influence_distance: 0.5,
+        };
+        let straight_plan = |v: f64| Plan {
+            velocity: [v, 0.0].into(),
+            cost: 0.0,
+            path: (1..=10)
+                .map(|i| Pose::new(Vector2::new(v * 0.1 * i as f64, 0.0), 0.0))
+                .collect(),
+            cost_breakdown: HashMap::new(),
+        };
+        // reaches the crossing point at the same time as the obstacle
+        assert_eq!(critic.cost(&straight_plan(1.0), &context), f64::MAX);
+        // passes behind the obstacle
+        let cost = critic.cost(&straight_plan(0.1), &context);
+        assert!(cost > 0.0 && cost < f64::MAX);
+        // no obstacles
+        let context = CriticContext {
+            dynamic_obstacles: &[],
+            ..context
+        };
+        assert_eq!(critic.cost(&straight_plan(1.0), &context), 0.0);
+    }
+
+    #[test]
+    fn critic_config_test() {
+        let critics: Vec<CriticConfig> = serde_yaml::from_str(
+            r#"
+- type: layer
+  name: local_goal
+- type: heading_alignment
+  name: rotation
+  angle: current_heading
+  look_ahead_samples: 3
+- type: twirling
+  name: twirling
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            critics[0],
+            CriticConfig::Layer(LayerCritic {
+                name: "local_goal".to_owned(),
+                layer: None,
+            })
+        );
+        assert_eq!(critics[1].name(), "rotation");
+        assert_eq!(critics[2].name(), "twirling");
+        assert!(
+            serde_yaml::from_str::<Vec<CriticConfig>>("- type: twirling\n  name: a\n  b: 1")
+                .is_err()
+        );
+    }
+}
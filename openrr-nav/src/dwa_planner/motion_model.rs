@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Limits, Velocity};
+use crate::Error;
+
+/// Kinematic model of the robot used to sample the velocities
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MotionModel {
+    /// Differential drive robot. It is also holonomic if the limits allow the y velocity.
+    #[default]
+    Unicycle,
+    /// Car-like robot
+    Ackermann(AckermannModel),
+}
+
+/// Car-like robot which samples the speed and the steering angle
+///
+/// The turning velocity is derived from them, so the theta limits are not used.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AckermannModel {
+    /// distance between the front and the rear axles [m]
+    pub wheel_base: f64,
+    /// minimum turning radius of the rear axle center [m]
+    pub min_turning_radius: f64,
+    /// maximum speed of the steering angle [rad/s]
+    pub max_steering_rate: f64,
+}
+
+impl MotionModel {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        match self {
+            Self::Unicycle => Ok(()),
+            Self::Ackermann(model) => model.validate(),
+        }
+    }
+
+    /// Velocity which the robot can realize, used for the velocities of the forward simulation
+    pub(crate) fn feasible_velocity(&self, velocity: &Velocity) -> Velocity {
+        match self {
+            Self::Unicycle => velocity.to_owned(),
+            Self::Ackermann(model) => model.clamp_turning(velocity),
+        }
+    }
+}
+
+impl AckermannModel {
+    fn validate(&self) -> Result<(), Error> {
+        for (field, value) in [
+            ("motion_model.wheel_base", self.wheel_base),
+            ("motion_model.min_turning_radius", self.min_turning_radius),
+        ] {
+            if value <= 0.0 {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be positive, but {value}"),
+                ));
+            }
+        }
+        if self.max_steering_rate < 0.0 {
+            return Err(Error::invalid_config(
+                "motion_model.max_steering_rate",
+                format!("must not be negative, but {}", self.max_steering_rate),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Maximum steering angle given by the minimum turning radius
+    pub fn max_steering_angle(&self) -> f64 {
+        (self.wheel_base / self.min_turning_radius).atan()
+    }
+
+    /// Steering angle which realizes the velocity
+    ///
+    /// The steering angle can't be observed while the robot stops, so it is 0 in that case.
+    pub fn steering_angle(&self, velocity: &Velocity) -> f64 {
+        if velocity.x.abs() < f64::EPSILON {
+            return 0.0;
+        }
+        let max_steering_angle = self.max_steering_angle();
+        (self.wheel_base * velocity.theta / velocity.x)
+            .atan()
+            .clamp(-max_steering_angle, max_steering_angle)
+    }
+
+    /// Velocity whose turning velocity is clamped by the minimum turning radius
+    ///
+    /// The robot can't turn in place, so the turning velocity is 0 while it stops.
+    pub fn clamp_turning(&self, velocity: &Velocity) -> Velocity {
+        let max_theta = velocity.x.abs() / self.min_turning_radius;
+        Velocity {
+            theta: velocity.theta.clamp(-max_theta, max_theta),
+            ..velocity.to_owned()
+        }
+    }
+
+    /// Velocity for the speed and the steering angle
+    pub fn velocity(&self, speed: f64, steering_angle: f64) -> Velocity {
+        Velocity {
+            x: speed,
+            y: 0.0,
+            theta: speed * steering_angle.tan() / self.wheel_base,
+        }
+    }
+
+    /// Get candidate velocities from current velocity
+    pub(crate) fn sample_velocity(
+        &self,
+        current_velocity: &Velocity,
+        limits: &Limits,
+        dt: f64,
+        num_vel_sample: i32,
+    ) -> Vec<Velocity> {
+        let max_speed = (current_velocity.x + limits.max_accel.x * dt)
+            .clamp(limits.min_velocity.x, limits.max_velocity.x);
+        let min_speed = (current_velocity.x + limits.min_accel.x * dt)
+            .clamp(limits.min_velocity.x, limits.max_velocity.x);
+        let max_steering_angle = self.max_steering_angle();
+        let current_steering_angle = self.steering_angle(current_velocity);
+        let max_steering = (current_steering_angle + self.max_steering_rate * dt)
+            .clamp(-max_steering_angle, max_steering_angle);
+        let min_steering = (current_steering_angle - self.max_steering_rate * dt)
+            .clamp(-max_steering_angle, max_steering_angle);
+        let d_speed = (max_speed - min_speed) / num_vel_sample as f64;
+        let d_steering = (max_steering - min_steering) / num_vel_sample as f64;
+        let mut velocities = vec![];
+        for i in 0..(num_vel_sample + 1) {
+            let steering_angle = min_steering + d_steering * i as f64;
+            for j in 0..(num_vel_sample + 1) {
+                velocities.push(self.velocity(min_speed + d_speed * j as f64, steering_angle));
+            }
+        }
+        velocities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_model() -> AckermannModel {
+        AckermannModel {
+            wheel_base: 0.5,
+            min_turning_radius: 1.0,
+            max_steering_rate: 1.0,
+        }
+    }
+
+    fn new_limits() -> Limits {
+        Limits {
+            max_velocity: [1.0, 2.0].into(),
+            max_accel: [1.0, 2.0].into(),
+            min_velocity: [-0.5, -2.0].into(),
+            min_accel: [-1.0, -2.0].into(),
+        }
+    }
+
+    #[test]
+    fn ackermann_sample_velocity_test() {
+        let model = new_model();
+        let limits = new_limits();
+        let current = model.velocity(0.5, 0.1);
+        assert!((model.steering_angle(&current) - 0.1).abs() < 1e-9);
+
+        let velocities = model.sample_velocity(&current, &limits, 0.1, 4);
+        assert_eq!(velocities.len(), 25);
+        for v in &velocities {
+            assert_eq!(v.y, 0.0);
+            assert!((0.4 - 1e-9..=0.6 + 1e-9).contains(&v.x));
+            // never turns tighter than the minimum radius
+            assert!(v.theta.abs() <= v.x.abs() / model.min_turning_radius + 1e-9);
+            // steering rate limit
+            if v.x.abs() > 1e-9 {
+                let steering = model.steering_angle(v);
+                assert!((-1e-9..=0.2 + 1e-9).contains(&steering));
+            }
+        }
+    }
+
+    #[test]
+    fn ackermann_steering_limit_test() {
+        let model = new_model();
+        let limits = new_limits();
+        let max_steering_angle = model.max_steering_angle();
+        let current = model.velocity(0.5, max_steering_angle);
+        let velocities = model.sample_velocity(&current, &limits, 0.1, 4);
+        let max_theta = velocities
+            .iter()
+            .map(|v| v.theta / v.x)
+            .fold(f64::MIN, f64::max);
+        assert!((max_theta - 1.0 / model.min_turning_radius).abs() < 1e-9);
+
+        // can't turn in place
+        let clamped = model.clamp_turning(&[0.5, 2.0].into());
+        assert!((clamped.theta - 0.5).abs() < 1e-9);
+        let clamped = model.clamp_turning(&[-0.5, -2.0].into());
+        assert!((clamped.theta + 0.5).abs() < 1e-9);
+        assert_eq!(model.clamp_turning(&[0.0, 1.0].into()).theta, 0.0);
+        let velocities = model.sample_velocity(&Velocity::default(), &limits, 0.1, 4);
+        for v in velocities {
+            if v.x == 0.0 {
+                assert_eq!(v.theta, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn motion_model_config_test() {
+        let model: MotionModel = serde_yaml::from_str(
+            r#"
+type: ackermann
+wheel_base: 0.5
+min_turning_radius: 1.0
+max_steering_rate: 1.0
+"#,
+        )
+        .unwrap();
+        assert_eq!(model, MotionModel::Ackermann(new_model()));
+        let model: MotionModel = serde_yaml::from_str("type: unicycle").unwrap();
+        assert_eq!(model, MotionModel::Unicycle);
+    }
+}
@@ -13,13 +13,15 @@ pub(crate) fn serialize<S: Serializer>(
     data: &HashMap<String, f64>,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
-    let pairs = data
+    let mut pairs = data
         .iter()
         .map(|(name, value)| CostNameWeightPair {
             name: name.clone(),
             value: *value,
         })
         .collect::<Vec<_>>();
+    // sort to make the output stable
+    pairs.sort_by(|a, b| a.name.cmp(&b.name));
     pairs.serialize(serializer)
 }
 
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Limits, Velocity};
+use crate::Error;
+
+/// How the velocity changes from the current one to the sampled one in the forward simulation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VelocityProfile {
+    /// The sampled velocity is reached immediately and held.
+    #[default]
+    Immediate,
+    /// The velocity ramps toward the sampled one under the acceleration limits and then is held.
+    Ramp,
+    /// The velocity ramps toward the sampled one, is held for `hold_duration` [s],
+    /// and then ramps down to stop.
+    RampThenStop { hold_duration: f64 },
+}
+
+impl VelocityProfile {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        match self {
+            Self::RampThenStop { hold_duration } if *hold_duration < 0.0 => {
+                Err(Error::invalid_config(
+                    "velocity_profile.hold_duration",
+                    format!("must not be negative, but {hold_duration}"),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Velocities of each step of the forward simulation
+    pub fn velocities(
+        &self,
+        current_velocity: &Velocity,
+        target_velocity: &Velocity,
+        limits: &Limits,
+        dt: f64,
+        num_steps: usize,
+    ) -> Vec<Velocity> {
+        let mut velocity = current_velocity.to_owned();
+        let mut reached_time = None;
+        let mut velocities = Vec::with_capacity(num_steps);
+        for i in 0..num_steps {
+            let elapsed = i as f64 * dt;
+            let target = match self {
+                Self::Immediate => {
+                    velocities.push(target_velocity.to_owned());
+                    continue;
+                }
+                Self::Ramp => target_velocity.to_owned(),
+                Self::RampThenStop { hold_duration } => match reached_time {
+                    Some(t) if elapsed >= t + hold_duration => Velocity::default(),
+                    _ => target_velocity.to_owned(),
+                },
+            };
+            velocity = accelerate(&velocity, &target, limits, dt);
+            if reached_time.is_none() && velocity == *target_velocity {
+                reached_time = Some(elapsed + dt);
+            }
+            velocities.push(velocity.to_owned());
+        }
+        velocities
+    }
+}
+
+fn accelerate(current: &Velocity, target: &Velocity, limits: &Limits, dt: f64) -> Velocity {
+    let step = |current: f64, target: f64, max_accel: f64, min_accel: f64| {
+        let diff = target - current;
+        if diff > max_accel * dt {
+            current + max_accel * dt
+        } else if diff < min_accel * dt {
+            current + min_accel * dt
+        } else {
+            target
+        }
+    };
+    Velocity {
+        x: step(current.x, target.x, limits.max_accel.x, limits.min_accel.x),
+        y: step(current.y, target.y, limits.max_accel.y, limits.min_accel.y),
+        theta: step(
+            current.theta,
+            target.theta,
+            limits.max_accel.theta,
+            limits.min_accel.theta,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_limits() -> Limits {
+        Limits {
+            max_velocity: [1.0, 2.0].into(),
+            max_accel: [1.0, 2.0].into(),
+            min_velocity: [-0.5, -2.0].into(),
+            min_accel: [-2.0, -2.0].into(),
+        }
+    }
+
+    #[test]
+    fn ramp_test() {
+        let limits = new_limits();
+        let target = [0.5, 0.1].into();
+        let velocities =
+            VelocityProfile::Ramp.velocities(&Velocity::default(), &target, &limits, 0.1, 8);
+        let xs = velocities.iter().map(|v| v.x).collect::<Vec<_>>();
+        for (x, expected) in xs.iter().zip([0.1, 0.2, 0.3, 0.4, 0.5, 0.5, 0.5, 0.5]) {
+            assert!((x - expected).abs() < 1e-9);
+        }
+        assert!(velocities.iter().all(|v| (v.theta - 0.1).abs() < 1e-9));
+
+        // decelerates with min_accel
+        let velocities = VelocityProfile::Ramp.velocities(
+            &[0.5, 0.0].into(),
+            &Velocity::default(),
+            &limits,
+            0.1,
+            3,
+        );
+        assert!((velocities[0].x - 0.3).abs() < 1e-9);
+        assert!((velocities[1].x - 0.1).abs() < 1e-9);
+        assert_eq!(velocities[2].x, 0.0);
+
+        let velocities =
+            VelocityProfile::Immediate.velocities(&Velocity::default(), &target, &limits, 0.1, 3);
+        assert!(velocities.iter().all(|v| *v == target));
+    }
+
+    #[test]
+    fn ramp_then_stop_test() {
+        let limits = new_limits();
+        let profile = VelocityProfile::RampThenStop { hold_duration: 0.2 };
+        let velocities =
+            profile.velocities(&Velocity::default(), &[0.2, 0.0].into(), &limits, 0.1, 8);
+        let xs = velocities.iter().map(|v| v.x).collect::<Vec<_>>();
+        for (x, expected) in xs.iter().zip([0.1, 0.2, 0.2, 0.2, 0.0, 0.0, 0.0, 0.0]) {
+            assert!((x - expected).abs() < 1e-9, "{xs:?}");
+        }
+    }
+
+    #[test]
+    fn velocity_profile_config_test() {
+        let profile: VelocityProfile =
+            serde_yaml::from_str("type: ramp_then_stop\nhold_duration: 0.5").unwrap();
+        assert_eq!(
+            profile,
+            VelocityProfile::RampThenStop { hold_duration: 0.5 }
+        );
+        let profile: VelocityProfile = serde_yaml::from_str("type: ramp").unwrap();
+        assert_eq!(profile, VelocityProfile::Ramp);
+    }
+}
@@ -7,8 +7,19 @@ pub enum Error {
     IoError(#[from] std::io::Error),
     #[error("grid_map: {0:?}")]
     GridError(#[from] grid_map::Error),
+    #[error("invalid config `{field}`: {message}")]
+    InvalidConfig { field: String, message: String },
     #[error("{0}")]
     Other(String),
 }
 
+impl Error {
+    pub(crate) fn invalid_config(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::InvalidConfig {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,417 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use grid_map::{Cell, Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::{cuts_corner, grid_neighbors, STRAIGHT_STEP_COST},
+    Error, Result,
+};
+
+/// Parameters of [`FrontierExplorer`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct FrontierConfig {
+    /// frontiers with fewer cells are ignored
+    pub min_frontier_size: usize,
+    /// cells with the value less than or equal to it are free [%]
+    pub free_threshold: u8,
+    /// unknown cells within it from the centroid are the information gain [m]
+    pub information_radius: f64,
+    /// weight of the information gain [m^2] in the score
+    pub gain_weight: f64,
+    /// weight of the path cost [m] in the score
+    pub distance_weight: f64,
+}
+
+impl Default for FrontierConfig {
+    fn default() -> Self {
+        Self {
+            min_frontier_size: 5,
+            free_threshold: 40,
+            information_radius: 1.0,
+            gain_weight: 1.0,
+            distance_weight: 1.0,
+        }
+    }
+}
+
+impl FrontierConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.free_threshold > 100 {
+            return Err(Error::invalid_config(
+                "free_threshold",
+                format!("must be at most 100, but {}", self.free_threshold),
+            ));
+        }
+        if !(self.information_radius.is_finite() && self.information_radius >= 0.0) {
+            return Err(Error::invalid_config(
+                "information_radius",
+                format!("must be non-negative, but {}", self.information_radius),
+            ));
+        }
+        for (field, value) in [
+            ("gain_weight", self.gain_weight),
+            ("distance_weight", self.distance_weight),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be non-negative, but {value}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connected free cells next to the unknown cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frontier {
+    pub cells: Vec<Grid>,
+    /// mean position of the cells, which may not be free
+    pub centroid: Position,
+    /// area of the unknown cells around the centroid [m^2]
+    pub information_gain: f64,
+}
+
+impl Frontier {
+    /// Number of the cells
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Next target of the exploration
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontierGoal {
+    pub frontier: Frontier,
+    /// the frontier cell nearest to the robot along the path
+    pub position: Position,
+    /// length of the path from the robot [m]
+    pub path_cost: f64,
+    /// `gain_weight * information_gain - distance_weight * path_cost`
+    pub score: f64,
+}
+
+/// Frontier detection and goal selection for autonomous exploration
+///
+/// The cells are the occupancy [%] and the unexplored cells are `Cell::Unknown`, same as
+/// [`OccupancyGridMap::to_grid_map`](grid_map::OccupancyGridMap::to_grid_map). The cells with
+/// the occupancy up to [`FrontierConfig::free_threshold`] are free.
+#[derive(Debug, Clone)]
+pub struct FrontierExplorer {
+    config: FrontierConfig,
+}
+
+impl FrontierExplorer {
+    pub fn new(config: FrontierConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self { config })
+    }
+
+    pub fn config(&self) -> &FrontierConfig {
+        &self.config
+    }
+
+    /// Find the frontiers, clustered by the 8-neighbors
+    pub fn frontiers(&self, map: &GridMap<u8>) -> Vec<Frontier> {
+        let width = map.width();
+        let is_frontier = (0..map.len())
+            .map(|index| self.is_frontier_cell(map, Grid::new(index % width, index / width)))
+            .collect::<Vec<_>>();
+        let mut visited = vec![false; map.len()];
+        let mut frontiers = vec![];
+        for index in 0..map.len() {
+            if !is_frontier[index] || visited[index] {
+                continue;
+            }
+            visited[index] = true;
+            let mut cells = vec![];
+            let mut queue = VecDeque::from([Grid::new(index % width, index / width)]);
+            while let Some(grid) = queue.pop_front() {
+                cells.push(grid);
+                for (neighbor, _) in grid_neighbors(map, grid) {
+                    let neighbor_index = neighbor.y * width + neighbor.x;
+                    if is_frontier[neighbor_index] && !visited[neighbor_index] {
+                        visited[neighbor_index] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            if cells.len() < self.config.min_frontier_size {
+                continue;
+            }
+            let centroid = centroid(map, &cells);
+            frontiers.push(Frontier {
+                information_gain: self.information_gain(map, &centroid),
+                cells,
+                centroid,
+            });
+        }
+        frontiers
+    }
+
+    /// Select the frontier with the best score among the reachable ones
+    ///
+    /// The path cost is the length of the shortest path on the free cells from the robot.
+    /// Returns `None` if no frontier is reachable, which means the exploration has finished.
+    pub fn select_goal(&self, map: &GridMap<u8>, robot: &Position) -> Option<FrontierGoal> {
+        let start = map.to_grid(robot.x, robot.y)?;
+        let costs = self.path_costs(map, start);
+        let resolution = map.resolution();
+        self.frontiers(map)
+            .into_iter()
+            .filter_map(|frontier| {
+                let (cost, grid) = frontier
+                    .cells
+                    .iter()
+                    .filter_map(|g| costs[g.y * map.width() + g.x].map(|c| (c, *g)))
+                    .min_by_key(|(c, _)| *c)?;
+                let path_cost = cost as f64 / STRAIGHT_STEP_COST as f64 * resolution;
+                Some(FrontierGoal {
+                    position: map.to_position(&grid)?,
+                    score: self.config.gain_weight * frontier.information_gain
+                        - self.config.distance_weight * path_cost,
+                    path_cost,
+                    frontier,
+                })
+            })
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    fn is_free(&self, map: &GridMap<u8>, grid: &Grid) -> bool {
+        matches!(map.cell(grid), Some(Cell::Value(v)) if *v <= self.config.free_threshold)
+    }
+
+    fn is_frontier_cell(&self, map: &GridMap<u8>, grid: Grid) -> bool {
+        self.is_free(map, &grid)
+            && grid_neighbors(map, grid).any(|(neighbor, cost)| {
+                cost == STRAIGHT_STEP_COST && matches!(map.cell(&neighbor), Some(Cell::Unknown))
+            })
+    }
+
+    /// Dijkstra on the free cells from the start, without cutting the corners
+    fn path_costs(&self, map: &GridMap<u8>, start: Grid) -> Vec<Option<u64>> {
+        let width = map.width();
+        let is_free = |grid: &Grid| self.is_free(map, grid);
+        let mut costs = vec![None; map.len()];
+        costs[start.y * width + start.x] = Some(0);
+        let mut queue = BinaryHeap::from([Reverse((0, start.y * width + start.x))]);
+        while let Some(Reverse((cost, index))) = queue.pop() {
+            if costs[index].is_some_and(|c| c < cost) {
+                continue;
+            }
+            let grid = Grid::new(index % width, index / width);
+            for (neighbor, step) in grid_neighbors(map, grid) {
+                if !is_free(&neighbor) || cuts_corner(&grid, &neighbor, is_free) {
+                    continue;
+                }
+                let neighbor_index = neighbor.y * width + neighbor.x;
+                let candidate = cost + step;
+                if !matches!(costs[neighbor_index], Some(c) if c <= candidate) {
+                    costs[neighbor_index] = Some(candidate);
+                    queue.push(Reverse((candidate, neighbor_index)));
+                }
+            }
+        }
+        costs
+    }
+
+    fn information_gain(&self, map: &GridMap<u8>, centroid: &Position) -> f64 {
+        let resolution = map.resolution();
+        let radius = self.config.information_radius;
+        let cells = (radius / resolution).ceil() as i64;
+        let Some(center) = map.to_grid(centroid.x, centroid.y) else {
+            return 0.0;
+        };
+        let mut unknown = 0;
+        for dy in -cells..=cells {
+            for dx in -cells..=cells {
+                if ((dx * dx + dy * dy) as f64).sqrt() * resolution > radius {
+                    continue;
+                }
+                let (x, y) = (center.x as i64 + dx, center.y as i64 + dy);
+                if x < 0 || y < 0 {
+                    continue;
+                }
+                if let Some(Cell::Unknown) = map.cell(&Grid::new(x as usize, y as usize)) {
+                    unknown += 1;
+                }
+            }
+        }
+        unknown as f64 * resolution * resolution
+    }
+}
+
+fn centroid(map: &GridMap<u8>, cells: &[Grid]) -> Position {
+    let (sum_x, sum_y) = cells
+        .iter()
+        .filter_map(|g| map.to_position(g))
+        .fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+    Position::new(sum_x / cells.len() as f64, sum_y / cells.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// known free cells in x < 4 [m], and an unknown pocket of 3x3 cells near the robot
+    fn new_map() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(6.0, 3.0), 0.1);
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let grid = Grid::new(x, y);
+                let pocket = (5..8).contains(&x) && (5..8).contains(&y);
+                if x < 40 && !pocket {
+                    map.set_value(&grid, 0).unwrap();
+                } else {
+                    *map.cell_mut(&grid).unwrap() = Cell::Unknown;
+                }
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn frontiers_test() {
+        let map = new_map();
+        let explorer = FrontierExplorer::new(Default::default()).unwrap();
+        let mut frontiers = explorer.frontiers(&map);
+        frontiers.sort_by_key(|f| f.size());
+        assert_eq!(frontiers.len(), 2);
+        // around the pocket
+        let pocket = &frontiers[0];
+        assert_eq!(pocket.size(), 12);
+        assert!((pocket.centroid.x - 0.65).abs() < 1e-9);
+        assert!((pocket.centroid.y - 0.65).abs() < 1e-9);
+        assert!((pocket.information_gain - 0.09).abs() < 1e-9);
+        // the boundary of the unknown area
+        let boundary = &frontiers[1];
+        assert_eq!(boundary.size(), 30);
+        assert!(boundary.cells.iter().all(|g| g.x == 39));
+        assert!((boundary.centroid.x - 3.95).abs() < 1e-9);
+        assert!(
+            boundary.information_gain > 1.0
+                && boundary.information_gain < std::f64::consts::PI / 2.0
+        );
+
+        let explorer = FrontierExplorer::new(FrontierConfig {
+            min_frontier_size: 20,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(explorer.frontiers(&map).len(), 1);
+    }
+
+    #[test]
+    fn select_goal_test() {
+        let mut map = new_map();
+        let robot = Position::new(0.55, 1.55);
+        // the nearer one
+        let explorer = FrontierExplorer::new(Default::default()).unwrap();
+        let goal = explorer.select_goal(&map, &robot).unwrap();
+        assert_eq!(goal.frontier.size(), 12);
+        assert!((goal.position.x - 0.55).abs() < 1e-9);
+        assert!((goal.position.y - 0.85).abs() < 1e-9);
+        assert!((goal.path_cost - 0.7).abs() < 1e-9);
+
+        // the larger one
+        let explorer = FrontierExplorer::new(FrontierConfig {
+            distance_weight: 0.1,
+            ..Default::default()
+        })
+        .unwrap();
+        let goal = explorer.select_goal(&map, &robot).unwrap();
+        assert_eq!(goal.frontier.size(), 30);
+        assert!((goal.position.x - 3.95).abs() < 1e-9);
+        assert!((goal.path_cost - 3.4).abs() < 1e-9);
+        assert!((goal.score - (goal.frontier.information_gain - 0.34)).abs() < 1e-9);
+
+        // unreachable behind the wall
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let goal = explorer.select_goal(&map, &robot).unwrap();
+        assert_eq!(goal.frontier.size(), 12);
+
+        // explored
+        for cell in map.cells_mut() {
+            if matches!(cell, Cell::Unknown) {
+                *cell = Cell::Value(0);
+            }
+        }
+        assert!(explorer.frontiers(&map).is_empty());
+        assert_eq!(explorer.select_goal(&map, &robot), None);
+    }
+
+    #[test]
+    fn reachability_test() {
+        let mut map = new_map();
+        let robot = Position::new(0.55, 1.55);
+        let config = FrontierConfig {
+            distance_weight: 0.1,
+            ..Default::default()
+        };
+        // likely occupied, as the cells from `OccupancyGridMap::to_grid_map`
+        for y in 0..map.height() {
+            map.set_value(&Grid::new(30, y), 60).unwrap();
+        }
+        let explorer = FrontierExplorer::new(config.clone()).unwrap();
+        assert_eq!(
+            explorer.select_goal(&map, &robot).unwrap().frontier.size(),
+            12
+        );
+        let explorer = FrontierExplorer::new(FrontierConfig {
+            free_threshold: 60,
+            ..config.clone()
+        })
+        .unwrap();
+        assert_eq!(
+            explorer.select_goal(&map, &robot).unwrap().frontier.size(),
+            30
+        );
+
+        // the diagonal gap in the wall cuts the corners
+        for y in 0..map.height() {
+            if y == 10 {
+                map.set_value(&Grid::new(30, y), 0).unwrap();
+            } else {
+                map.set_obstacle(&Grid::new(30, y)).unwrap();
+            }
+            if y != 11 {
+                map.set_obstacle(&Grid::new(31, y)).unwrap();
+            }
+        }
+        let explorer = FrontierExplorer::new(config).unwrap();
+        assert_eq!(
+            explorer.select_goal(&map, &robot).unwrap().frontier.size(),
+            12
+        );
+        map.set_value(&Grid::new(31, 10), 0).unwrap();
+        assert_eq!(
+            explorer.select_goal(&map, &robot).unwrap().frontier.size(),
+            30
+        );
+    }
+
+    #[test]
+    fn frontier_config_test() {
+        let config = FrontierConfig {
+            information_radius: -1.0,
+            ..Default::default()
+        };
+        assert!(FrontierExplorer::new(config).is_err());
+        let config = FrontierConfig {
+            free_threshold: 101,
+            ..Default::default()
+        };
+        assert!(FrontierExplorer::new(config).is_err());
+        let config: FrontierConfig = serde_yaml::from_str("gain_weight: 2.0").unwrap();
+        assert_eq!(config.gain_weight, 2.0);
+        assert_eq!(config.min_frontier_size, 5);
+    }
+}
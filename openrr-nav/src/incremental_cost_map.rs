@@ -0,0 +1,333 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use grid_map::{Cell, Error, Grid, GridMap, Result};
+
+const UNREACHABLE: u32 = u32::MAX;
+const OBSTACLE_DISTANCE_MAX_VALUE: u32 = 50;
+const OBSTACLE_DISTANCE_REDUCE: u32 = 10;
+
+/// Where the distances are measured from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DistanceMapKind {
+    /// same as [`path_distance_map`](crate::path_distance_map)
+    Path(Vec<Grid>),
+    /// same as [`goal_distance_map`](crate::goal_distance_map)
+    Goal(Grid),
+    /// same as [`obstacle_distance_map`](crate::obstacle_distance_map)
+    Obstacle,
+}
+
+/// Distance map which is repaired only around the changed cells
+///
+/// The result is the same as the map created from scratch by the function of the kind.
+#[derive(Debug, Clone)]
+pub struct IncrementalDistanceMap {
+    kind: DistanceMapKind,
+    /// cells of the source map
+    cells: Vec<Cell<u8>>,
+    /// number of the steps from the nearest source
+    distances: Vec<u32>,
+    /// whether the cell is a source, for the path and the goal
+    is_source: Vec<bool>,
+    /// scratch of [`IncrementalDistanceMap::repair`], which is all false between the updates
+    invalid: Vec<bool>,
+    output: GridMap<u8>,
+}
+
+impl IncrementalDistanceMap {
+    /// Create the map from scratch
+    pub fn new(map: &GridMap<u8>, kind: DistanceMapKind) -> Result<Self> {
+        let mut is_source = vec![false; map.len()];
+        let seeds = match &kind {
+            DistanceMapKind::Path(path) => path.as_slice(),
+            DistanceMapKind::Goal(goal) => std::slice::from_ref(goal),
+            DistanceMapKind::Obstacle => &[],
+        };
+        for grid in seeds {
+            let index = to_index(map, grid).ok_or(Error::OutOfRangeGrid(*grid))?;
+            is_source[index] = true;
+        }
+        let mut distance_map = Self {
+            kind,
+            cells: map.cells().clone(),
+            distances: vec![UNREACHABLE; map.len()],
+            is_source,
+            invalid: vec![false; map.len()],
+            output: map.copy_without_value(),
+        };
+        let all = (0..map.len()).collect::<Vec<_>>();
+        distance_map.repair(&all);
+        Ok(distance_map)
+    }
+
+    pub fn path_distance(map: &GridMap<u8>, path: &[Grid]) -> Result<Self> {
+        Self::new(map, DistanceMapKind::Path(path.to_vec()))
+    }
+
+    pub fn goal_distance(map: &GridMap<u8>, goal: &Grid) -> Result<Self> {
+        Self::new(map, DistanceMapKind::Goal(*goal))
+    }
+
+    pub fn obstacle_distance(map: &GridMap<u8>) -> Result<Self> {
+        Self::new(map, DistanceMapKind::Obstacle)
+    }
+
+    pub fn kind(&self) -> &DistanceMapKind {
+        &self.kind
+    }
+
+    /// The distance map as the cost layer
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.output
+    }
+
+    /// Number of the steps from the nearest source, or `None` if it is unreachable
+    pub fn distance(&self, grid: &Grid) -> Option<u32> {
+        let index = to_index(&self.output, grid)?;
+        Some(self.distances[index]).filter(|d| *d != UNREACHABLE)
+    }
+
+    /// Apply the changes of the cells in `map` to the distances
+    ///
+    /// `map` must have the same size as the map given at the creation.
+    pub fn update(
+        &mut self,
+        map: &GridMap<u8>,
+        changed: impl IntoIterator<Item = Grid>,
+    ) -> Result<()> {
+        if map.width() != self.output.width() || map.height() != self.output.height() {
+            return Err(Error::Other(format!(
+                "map size {}x{} is different from {}x{}",
+                map.width(),
+                map.height(),
+                self.output.width(),
+                self.output.height()
+            )));
+        }
+        let mut changed_indices = vec![];
+        for grid in changed {
+            let index = to_index(map, &grid).ok_or(Error::OutOfRangeGrid(grid))?;
+            let cell = map.cells()[index].to_owned();
+            if self.cells[index] != cell {
+                self.cells[index] = cell;
+                changed_indices.push(index);
+            }
+        }
+        self.repair(&changed_indices);
+        Ok(())
+    }
+
+    /// Apply the changes in the rectangle from `min` to `max` (inclusive)
+    pub fn update_rect(&mut self, map: &GridMap<u8>, min: &Grid, max: &Grid) -> Result<()> {
+        let grids = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| Grid::new(x, y)))
+            .collect::<Vec<_>>();
+        self.update(map, grids)
+    }
+
+    fn is_source(&self, index: usize) -> bool {
+        match self.kind {
+            DistanceMapKind::Obstacle => self.cells[index].is_obstacle(),
+            _ => self.is_source[index],
+        }
+    }
+
+    /// The distance can be propagated into the cell
+    fn is_passable(&self, index: usize) -> bool {
+        !matches!(self.cells[index], Cell::Obstacle | Cell::Unknown)
+    }
+
+    fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> {
+        let width = self.output.width();
+        let height = self.output.height();
+        let x = index % width;
+        let y = index / width;
+        [
+            (x + 1 < width).then(|| index + 1),
+            (x > 0).then(|| index - 1),
+            (y + 1 < height).then(|| index + width),
+            (y > 0).then(|| index - width),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Recompute the distances which depend on the changed cells
+    fn repair(&mut self, changed: &[usize]) {
+        // raise: invalidate the cells whose shortest paths may pass through the changed cells
+        // reused to keep the cost proportional to the changed region, not to the map size
+        let mut invalid = std::mem::take(&mut self.invalid);
+        let mut invalidated = vec![];
+        let mut stack = changed.to_vec();
+        while let Some(index) = stack.pop() {
+            if invalid[index] {
+                continue;
+            }
+            invalid[index] = true;
+            invalidated.push(index);
+            let distance = std::mem::replace(&mut self.distances[index], UNREACHABLE);
+            if distance == UNREACHABLE {
+                continue;
+            }
+            for neighbor in self.neighbors(index) {
+                if !invalid[neighbor] && self.distances[neighbor] == distance + 1 {
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        // lower: propagate from the sources and the valid cells around the invalidated region
+        let mut queue = BinaryHeap::new();
+        for &index in &invalidated {
+            if self.is_source(index) {
+                self.distances[index] = 0;
+                queue.push(Reverse((0, index)));
+                continue;
+            }
+            for neighbor in self.neighbors(index) {
+                let distance = self.distances[neighbor];
+                if !invalid[neighbor] && distance != UNREACHABLE {
+                    queue.push(Reverse((distance, neighbor)));
+                }
+            }
+        }
+        for &index in &invalidated {
+            invalid[index] = false;
+        }
+        self.invalid = invalid;
+        let mut touched = invalidated;
+        while let Some(Reverse((distance, index))) = queue.pop() {
+            if distance > self.distances[index] {
+                continue;
+            }
+            for neighbor in self.neighbors(index) {
+                if self.is_passable(neighbor) && distance + 1 < self.distances[neighbor] {
+                    self.distances[neighbor] = distance + 1;
+                    touched.push(neighbor);
+                    queue.push(Reverse((distance + 1, neighbor)));
+                }
+            }
+        }
+
+        for index in touched {
+            self.output.cells_mut()[index] = self.to_cell(index);
+        }
+    }
+
+    fn to_cell(&self, index: usize) -> Cell<u8> {
+        let distance = self.distances[index];
+        if distance == UNREACHABLE {
+            return match self.cells[index] {
+                Cell::Value(_) => Cell::Uninitialized,
+                ref c => c.to_owned(),
+            };
+        }
+        match self.kind {
+            DistanceMapKind::Path(_) | DistanceMapKind::Goal(_) => {
+                Cell::Value(distance.min(u8::MAX as u32) as u8)
+            }
+            DistanceMapKind::Obstacle => {
+                if distance == 0 {
+                    self.cells[index].to_owned()
+                } else {
+                    Cell::Value(
+                        OBSTACLE_DISTANCE_MAX_VALUE
+                            .saturating_sub(OBSTACLE_DISTANCE_REDUCE.saturating_mul(distance))
+                            as u8,
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn to_index(map: &GridMap<u8>, grid: &Grid) -> Option<usize> {
+    (grid.x < map.width() && grid.y < map.height()).then(|| grid.y * map.width() + grid.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{goal_distance_map, obstacle_distance_map, path_distance_map};
+    use grid_map::Position;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn new_map() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(2.05, 1.55), 0.05);
+        for i in 5..30 {
+            map.set_obstacle(&Grid::new(i, 10)).unwrap();
+            map.set_obstacle(&Grid::new(15, i)).unwrap();
+        }
+        map.cells_mut()[3] = Cell::Unknown;
+        map
+    }
+
+    fn full(map: &GridMap<u8>, kind: &DistanceMapKind) -> GridMap<u8> {
+        match kind {
+            DistanceMapKind::Path(path) => path_distance_map(map, path).unwrap(),
+            DistanceMapKind::Goal(goal) => goal_distance_map(map, goal).unwrap(),
+            DistanceMapKind::Obstacle => obstacle_distance_map(map).unwrap(),
+        }
+    }
+
+    #[test]
+    fn incremental_distance_map_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let path = (0..40).map(|i| Grid::new(i, 2)).collect::<Vec<_>>();
+        for kind in [
+            DistanceMapKind::Path(path),
+            DistanceMapKind::Goal(Grid::new(35, 25)),
+            DistanceMapKind::Obstacle,
+        ] {
+            let mut map = new_map();
+            let mut distance_map = IncrementalDistanceMap::new(&map, kind.clone()).unwrap();
+            assert_eq!(distance_map.map().cells(), full(&map, &kind).cells());
+            for _ in 0..30 {
+                // add or remove some obstacles
+                let mut changed = vec![];
+                for _ in 0..rng.gen_range(1..5) {
+                    let grid = Grid::new(
+                        rng.gen_range(0..map.width()),
+                        rng.gen_range(0..map.height()),
+                    );
+                    let cell = map.cell_mut(&grid).unwrap();
+                    *cell = match rng.gen_range(0..3) {
+                        0 => Cell::Obstacle,
+                        1 => Cell::Unknown,
+                        _ => Cell::Uninitialized,
+                    };
+                    changed.push(grid);
+                }
+                distance_map.update(&map, changed).unwrap();
+                assert_eq!(distance_map.map().cells(), full(&map, &kind).cells());
+                // the scratch is cleared for the next update
+                assert!(distance_map.invalid.iter().all(|invalid| !invalid));
+            }
+
+            // a wall which separates the map
+            for y in 0..map.height() {
+                map.set_obstacle(&Grid::new(20, y)).unwrap();
+            }
+            distance_map
+                .update_rect(&map, &Grid::new(20, 0), &Grid::new(20, map.height() - 1))
+                .unwrap();
+            assert_eq!(distance_map.map().cells(), full(&map, &kind).cells());
+        }
+    }
+
+    #[test]
+    fn incremental_distance_test() {
+        let map = new_map();
+        let distance_map = IncrementalDistanceMap::goal_distance(&map, &Grid::new(0, 0)).unwrap();
+        assert_eq!(distance_map.distance(&Grid::new(0, 0)), Some(0));
+        assert_eq!(distance_map.distance(&Grid::new(2, 1)), Some(3));
+        // Unknown cell
+        assert_eq!(distance_map.distance(&Grid::new(3, 0)), None);
+        assert_eq!(distance_map.distance(&Grid::new(1000, 0)), None);
+        assert!(IncrementalDistanceMap::goal_distance(&map, &Grid::new(1000, 0)).is_err());
+
+        let mut distance_map = distance_map;
+        let small = GridMap::new(Position::new(0.0, 0.0), Position::new(0.5, 0.5), 0.05);
+        assert!(distance_map.update(&small, []).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use nalgebra as na;
+
+use crate::Pose;
+
+/// 2D laser scan, in the same layout as `sensor_msgs/LaserScan`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaserScan {
+    /// angle of the first beam [rad]
+    pub angle_min: f64,
+    /// angle between the beams [rad]
+    pub angle_increment: f64,
+    pub range_min: f64,
+    pub range_max: f64,
+    /// ranges of the beams [m], `f64::INFINITY` or `NaN` if no return
+    pub ranges: Vec<f64>,
+}
+
+impl LaserScan {
+    /// Angle of the i-th beam in the sensor frame
+    pub fn angle(&self, index: usize) -> f64 {
+        self.angle_min + self.angle_increment * index as f64
+    }
+
+    /// Whether the range has a return within the measurable range
+    pub fn is_valid_range(&self, range: f64) -> bool {
+        range.is_finite() && range >= self.range_min && range < self.range_max
+    }
+
+    /// Iterate `(angle, range)` of the valid beams
+    pub fn valid_beams(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| self.is_valid_range(**r))
+            .map(|(i, r)| (self.angle(i), *r))
+    }
+
+    /// Hit points of the valid beams in the sensor frame
+    pub fn points(&self) -> Vec<na::Point2<f64>> {
+        self.valid_beams()
+            .map(|(angle, range)| na::Point2::new(range * angle.cos(), range * angle.sin()))
+            .collect()
+    }
+
+    /// Hit points of the valid beams transformed by the sensor pose
+    pub fn points_in(&self, sensor_pose: &Pose) -> Vec<na::Point2<f64>> {
+        self.points().into_iter().map(|p| sensor_pose * p).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laser_scan_points_test() {
+        let scan = LaserScan {
+            angle_min: -std::f64::consts::FRAC_PI_2,
+            angle_increment: std::f64::consts::FRAC_PI_2,
+            range_min: 0.1,
+            range_max: 10.0,
+            ranges: vec![1.0, f64::INFINITY, 2.0, 0.05, f64::NAN],
+        };
+        let points = scan.points();
+        assert_eq!(points.len(), 2);
+        assert!((points[0] - na::Point2::new(0.0, -1.0)).norm() < 1e-9);
+        assert!((points[1] - na::Point2::new(0.0, 2.0)).norm() < 1e-9);
+
+        let pose = Pose::new(na::Vector2::new(1.0, 1.0), std::f64::consts::FRAC_PI_2);
+        let points = scan.points_in(&pose);
+        assert!((points[0] - na::Point2::new(2.0, 1.0)).norm() < 1e-9);
+        assert!((points[1] - na::Point2::new(-1.0, 1.0)).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,286 @@
+use grid_map::{Cell, GridMap, Position};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{utils::sample_normal, Error, LaserScan, Pose, Result};
+
+/// Parameters of [`LaserScanSimulator`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct LaserScanSimulatorConfig {
+    /// angle of the first beam [rad]
+    pub angle_min: f64,
+    /// angle of the last beam [rad]
+    pub angle_max: f64,
+    /// angle between the beams [rad]
+    pub angle_increment: f64,
+    pub range_min: f64,
+    pub range_max: f64,
+    /// standard deviation of the Gaussian noise of the ranges [m]
+    pub noise_std_dev: f64,
+    /// probability of the beam having no return
+    pub dropout_probability: f64,
+    /// seed of the random number generator, which is random if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for LaserScanSimulatorConfig {
+    fn default() -> Self {
+        Self {
+            angle_min: -std::f64::consts::PI,
+            angle_max: std::f64::consts::PI - 1.0_f64.to_radians(),
+            angle_increment: 1.0_f64.to_radians(),
+            range_min: 0.05,
+            range_max: 10.0,
+            noise_std_dev: 0.01,
+            dropout_probability: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl LaserScanSimulatorConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.angle_increment.is_finite() && self.angle_increment > 0.0) {
+            return Err(Error::invalid_config(
+                "angle_increment",
+                format!("must be positive, but {}", self.angle_increment),
+            ));
+        }
+        if !(self.angle_min.is_finite()
+            && self.angle_max.is_finite()
+            && self.angle_min <= self.angle_max)
+        {
+            return Err(Error::invalid_config(
+                "angle_max",
+                format!("must be >= angle_min ({})", self.angle_min),
+            ));
+        }
+        if !(self.range_min >= 0.0 && self.range_min < self.range_max) {
+            return Err(Error::invalid_config(
+                "range_max",
+                format!("must be > range_min ({}) >= 0", self.range_min),
+            ));
+        }
+        // the end of the ray must be a finite position to be raytraced
+        if !self.range_max.is_finite() {
+            return Err(Error::invalid_config(
+                "range_max",
+                format!("must be finite, but {}", self.range_max),
+            ));
+        }
+        if !(self.noise_std_dev.is_finite() && self.noise_std_dev >= 0.0) {
+            return Err(Error::invalid_config(
+                "noise_std_dev",
+                format!("must be non-negative, but {}", self.noise_std_dev),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.dropout_probability) {
+            return Err(Error::invalid_config(
+                "dropout_probability",
+                format!("must be in [0, 1], but {}", self.dropout_probability),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of the beams from `angle_min` to `angle_max`
+    pub fn num_beams(&self) -> usize {
+        ((self.angle_max - self.angle_min) / self.angle_increment).round() as usize + 1
+    }
+}
+
+/// Laser scan simulator which casts the rays on a `GridMap`
+///
+/// The rays stop at `Cell::Obstacle`. The beams without return, out of the map or dropped out,
+/// are `f64::INFINITY`.
+#[derive(Debug)]
+pub struct LaserScanSimulator {
+    config: LaserScanSimulatorConfig,
+    rng: StdRng,
+}
+
+impl LaserScanSimulator {
+    pub fn new(config: LaserScanSimulatorConfig) -> Result<Self> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Ok(Self { config, rng })
+    }
+
+    pub fn config(&self) -> &LaserScanSimulatorConfig {
+        &self.config
+    }
+
+    /// Simulate the scan from the pose of the sensor in the map frame
+    pub fn scan(&mut self, map: &GridMap<u8>, sensor_pose: &Pose) -> LaserScan {
+        let mut scan = LaserScan {
+            angle_min: self.config.angle_min,
+            angle_increment: self.config.angle_increment,
+            range_min: self.config.range_min,
+            range_max: self.config.range_max,
+            ranges: Vec::with_capacity(self.config.num_beams()),
+        };
+        let origin = Position::new(sensor_pose.translation.x, sensor_pose.translation.y);
+        for i in 0..self.config.num_beams() {
+            let angle = sensor_pose.rotation.angle() + scan.angle(i);
+            let range = cast_ray(map, &origin, angle, self.config.range_max);
+            let range = match range {
+                Some(_) if self.rng.gen_bool(self.config.dropout_probability) => f64::INFINITY,
+                Some(range) => {
+                    let range = range + sample_normal(&mut self.rng, self.config.noise_std_dev);
+                    if range >= self.config.range_max {
+                        f64::INFINITY
+                    } else {
+                        range.max(self.config.range_min)
+                    }
+                }
+                None => f64::INFINITY,
+            };
+            scan.ranges.push(range);
+        }
+        scan
+    }
+}
+
+/// Distance to the first obstacle on the ray within `max_range`
+pub fn cast_ray(map: &GridMap<u8>, origin: &Position, angle: f64, max_range: f64) -> Option<f64> {
+    let (cos, sin) = (angle.cos(), angle.sin());
+    let end = Position::new(origin.x + max_range * cos, origin.y + max_range * sin);
+    let resolution = map.resolution();
+    for grid in map.raytrace(origin, &end) {
+        if !matches!(map.cell(&grid), Some(Cell::Obstacle)) {
+            continue;
+        }
+        // where the ray enters the cell
+        let min_x = map.min_point().x + grid.x as f64 * resolution;
+        let min_y = map.min_point().y + grid.y as f64 * resolution;
+        let entry = |min: f64, origin: f64, direction: f64| {
+            if direction == 0.0 {
+                f64::NEG_INFINITY
+            } else {
+                let t0 = (min - origin) / direction;
+                let t1 = (min + resolution - origin) / direction;
+                t0.min(t1)
+            }
+        };
+        let range = entry(min_x, origin.x, cos)
+            .max(entry(min_y, origin.y, sin))
+            .max(0.0);
+        return (range < max_range).then_some(range);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Grid;
+    use nalgebra as na;
+
+    fn new_map() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(2.0, 2.0), 0.1);
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(15, y)).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn cast_ray_test() {
+        let map = new_map();
+        let origin = Position::new(0.5, 1.0);
+        assert!((cast_ray(&map, &origin, 0.0, 5.0).unwrap() - 1.0).abs() < 1e-9);
+        let range = cast_ray(
+            &map,
+            &Position::new(0.5, 0.5),
+            std::f64::consts::FRAC_PI_4,
+            5.0,
+        )
+        .unwrap();
+        assert!((range - 2.0_f64.sqrt()).abs() < 1e-9);
+        // too far, and out of the map
+        assert_eq!(cast_ray(&map, &origin, 0.0, 0.9), None);
+        assert_eq!(cast_ray(&map, &origin, std::f64::consts::PI, 5.0), None);
+    }
+
+    #[test]
+    fn laser_scan_simulator_test() {
+        let map = new_map();
+        let config = LaserScanSimulatorConfig {
+            angle_min: -0.1,
+            angle_max: 0.1,
+            angle_increment: 0.001,
+            noise_std_dev: 0.02,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let pose = Pose::new(na::Vector2::new(0.5, 1.0), 0.0);
+        let mut simulator = LaserScanSimulator::new(config.clone()).unwrap();
+        let scan = simulator.scan(&map, &pose);
+        assert_eq!(scan.ranges.len(), 201);
+        assert_eq!(config.num_beams(), 201);
+        let errors = scan
+            .ranges
+            .iter()
+            .enumerate()
+            .map(|(i, r)| r - 1.0 / scan.angle(i).cos())
+            .collect::<Vec<_>>();
+        let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+        let std_dev =
+            (errors.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / errors.len() as f64).sqrt();
+        assert!(mean.abs() < 0.01);
+        assert!((std_dev - 0.02).abs() < 0.005);
+
+        // deterministic with the seed
+        let mut other = LaserScanSimulator::new(config.clone()).unwrap();
+        assert_eq!(other.scan(&map, &pose), scan);
+
+        let mut dropout = LaserScanSimulator::new(LaserScanSimulatorConfig {
+            dropout_probability: 0.5,
+            ..config
+        })
+        .unwrap();
+        let dropped = dropout
+            .scan(&map, &pose)
+            .ranges
+            .iter()
+            .filter(|r| r.is_infinite())
+            .count();
+        assert!((70..130).contains(&dropped), "{dropped}");
+
+        // facing the outside of the map
+        let scan = simulator.scan(&map, &Pose::new(na::Vector2::new(0.5, 1.0), 3.0));
+        assert!(scan.ranges.iter().all(|r| r.is_infinite()));
+        assert!(scan.points().is_empty());
+    }
+
+    #[test]
+    fn laser_scan_simulator_config_test() {
+        let config = LaserScanSimulatorConfig {
+            dropout_probability: 1.5,
+            ..Default::default()
+        };
+        assert!(LaserScanSimulator::new(config).is_err());
+        let config = LaserScanSimulatorConfig {
+            angle_min: 1.0,
+            angle_max: 0.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = LaserScanSimulatorConfig {
+            range_max: f64::INFINITY,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(Error::InvalidConfig { field, .. }) if field == "range_max"
+        ));
+        let config: LaserScanSimulatorConfig = serde_yaml::from_str("range_max: .inf").unwrap();
+        assert!(config.validate().is_err());
+        assert_eq!(LaserScanSimulatorConfig::default().num_beams(), 360);
+    }
+}
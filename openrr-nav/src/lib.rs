@@ -1,12 +1,42 @@
 // mod angle_table;
+mod amcl;
+mod benchmark;
 mod cost_map;
+mod coverage_planner;
 mod dwa_planner;
 mod error;
+mod frontier;
+mod incremental_cost_map;
+mod laser_scan;
+mod laser_scan_simulator;
+mod likelihood_field;
+mod moving_obstacle;
+mod navigation_config;
+mod obstacle_layer;
+mod pose_estimator;
 mod robot_path;
+mod robot_simulator;
+mod rolling_window;
+mod scan_matcher;
 pub mod utils;
 
 // pub use crate::angle_table::*;
+pub use crate::amcl::*;
+pub use crate::benchmark::*;
 pub use crate::cost_map::*;
+pub use crate::coverage_planner::*;
 pub use crate::dwa_planner::*;
 pub use crate::error::*;
+pub use crate::frontier::*;
+pub use crate::incremental_cost_map::*;
+pub use crate::laser_scan::*;
+pub use crate::laser_scan_simulator::*;
+pub use crate::likelihood_field::*;
+pub use crate::moving_obstacle::*;
+pub use crate::navigation_config::*;
+pub use crate::obstacle_layer::*;
+pub use crate::pose_estimator::*;
 pub use crate::robot_path::*;
+pub use crate::robot_simulator::*;
+pub use crate::rolling_window::*;
+pub use crate::scan_matcher::*;
@@ -0,0 +1,116 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use grid_map::{Cell, Grid, GridMap};
+
+/// Euclidean distance from each cell to the nearest obstacle [m]
+///
+/// The distances are capped at `max_distance`, which is also returned out of the map.
+#[derive(Debug, Clone)]
+pub struct LikelihoodField {
+    distances: GridMap<f64>,
+    max_distance: f64,
+}
+
+impl LikelihoodField {
+    pub fn new(map: &GridMap<u8>, max_distance: f64) -> Self {
+        let width = map.width();
+        let height = map.height();
+        let resolution = map.resolution();
+        let max_squared = (max_distance / resolution).powi(2);
+        // squared distance in cells, and the nearest obstacle
+        let mut nearest: Vec<Option<(u64, Grid)>> = vec![None; map.len()];
+        let mut queue = BinaryHeap::new();
+        for (index, cell) in map.cells().iter().enumerate() {
+            if cell.is_obstacle() {
+                let grid = Grid::new(index % width, index / width);
+                nearest[index] = Some((0, grid));
+                queue.push(Reverse((0, index)));
+            }
+        }
+        while let Some(Reverse((squared, index))) = queue.pop() {
+            let Some((current, source)) = nearest[index] else {
+                continue;
+            };
+            if squared > current {
+                continue;
+            }
+            let x = index % width;
+            let y = index / width;
+            for (nx, ny) in [
+                (x + 1 < width).then(|| (x + 1, y)),
+                (x > 0).then(|| (x - 1, y)),
+                (y + 1 < height).then(|| (x, y + 1)),
+                (y > 0).then(|| (x, y - 1)),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                let dx = nx.abs_diff(source.x) as u64;
+                let dy = ny.abs_diff(source.y) as u64;
+                let candidate = dx * dx + dy * dy;
+                if candidate as f64 > max_squared {
+                    continue;
+                }
+                let neighbor = ny * width + nx;
+                if !matches!(nearest[neighbor], Some((d, _)) if d <= candidate) {
+                    nearest[neighbor] = Some((candidate, source));
+                    queue.push(Reverse((candidate, neighbor)));
+                }
+            }
+        }
+
+        let mut distances = GridMap::new_with_size(
+            map.min_point().to_owned(),
+            grid_map::Size::new(width, height),
+            resolution,
+        );
+        for (cell, nearest) in distances.cells_mut().iter_mut().zip(&nearest) {
+            *cell = Cell::Value(match nearest {
+                Some((squared, _)) => ((*squared as f64).sqrt() * resolution).min(max_distance),
+                None => max_distance,
+            });
+        }
+        Self {
+            distances,
+            max_distance,
+        }
+    }
+
+    /// Distance to the nearest obstacle from the position
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        self.distances
+            .to_grid(x, y)
+            .and_then(|grid| self.distances.value(&grid))
+            .unwrap_or(self.max_distance)
+    }
+
+    pub fn map(&self) -> &GridMap<f64> {
+        &self.distances
+    }
+
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Position;
+
+    #[test]
+    fn likelihood_field_test() {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.1);
+        map.set_obstacle(&Grid::new(0, 0)).unwrap();
+        map.set_obstacle(&Grid::new(9, 9)).unwrap();
+        let field = LikelihoodField::new(&map, 0.5);
+        assert_eq!(field.distance(0.05, 0.05), 0.0);
+        assert!((field.distance(0.35, 0.05) - 0.3).abs() < 1e-9);
+        assert!((field.distance(0.35, 0.45) - 0.5).abs() < 1e-9);
+        assert!((field.distance(0.75, 0.85) - 0.2f64.hypot(0.1)).abs() < 1e-9);
+        // capped
+        assert_eq!(field.distance(0.55, 0.45), 0.5);
+        assert_eq!(field.distance(-1.0, 0.0), 0.5);
+        assert_eq!(field.max_distance(), 0.5);
+    }
+}
@@ -0,0 +1,49 @@
+use nalgebra as na;
+
+/// Tracked obstacle which moves at a constant velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovingObstacle {
+    /// position in the map frame [m]
+    pub position: na::Vector2<f64>,
+    /// velocity in the map frame [m/s]
+    pub velocity: na::Vector2<f64>,
+    /// radius of the circle which covers the obstacle [m]
+    pub radius: f64,
+}
+
+impl MovingObstacle {
+    pub fn new(position: na::Vector2<f64>, velocity: na::Vector2<f64>, radius: f64) -> Self {
+        Self {
+            position,
+            velocity,
+            radius,
+        }
+    }
+
+    /// Predicted position after `time` [s]
+    pub fn predicted_position(&self, time: f64) -> na::Vector2<f64> {
+        self.position + self.velocity * time
+    }
+
+    /// Distance between the surface of the obstacle after `time` [s] and the point
+    ///
+    /// It is negative if the point is inside the obstacle.
+    pub fn clearance(&self, point: &na::Vector2<f64>, time: f64) -> f64 {
+        (point - self.predicted_position(time)).norm() - self.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_obstacle_test() {
+        let obstacle =
+            MovingObstacle::new(na::Vector2::new(1.0, 0.0), na::Vector2::new(-0.5, 0.5), 0.2);
+        let p = obstacle.predicted_position(2.0);
+        assert!((p - na::Vector2::new(0.0, 1.0)).norm() < 1e-9);
+        assert!((obstacle.clearance(&na::Vector2::new(0.0, 0.0), 2.0) - 0.8).abs() < 1e-9);
+        assert!(obstacle.clearance(&na::Vector2::new(1.0, 0.0), 0.0) < 0.0);
+    }
+}
@@ -0,0 +1,512 @@
+use std::{fs, path::Path, path::PathBuf};
+
+use grid_map::GridMap;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{DwaPlanner, Error, LocalGoalMapConfig};
+
+/// Prefix of the environment variables which override the config
+///
+/// `OPENRR_NAV__GOAL_TOLERANCE__DISTANCE=0.2` overrides `goal_tolerance.distance`.
+pub const ENV_OVERRIDE_PREFIX: &str = "OPENRR_NAV__";
+
+const DEFAULT_LOCAL_PLANNER_CONFIG: &str = include_str!("../config/dwa_parameter_config.yaml");
+
+/// Config of the whole navigation stack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NavigationConfig {
+    #[serde(default)]
+    pub map: MapConfig,
+    #[serde(default)]
+    pub cost_maps: CostMapsConfig,
+    #[serde(default)]
+    pub global_planner: GlobalPlannerConfig,
+    /// same as the `DwaPlanner` section of the DWA planner config
+    ///
+    /// The weights may refer to the custom critics, so call [`DwaPlanner::validate_critics`]
+    /// after registering them.
+    #[serde(default = "default_local_planner")]
+    pub local_planner: DwaPlanner,
+    #[serde(default)]
+    pub goal_tolerance: GoalTolerance,
+    /// frequency of the control loop [Hz]
+    #[serde(default = "default_controller_frequency")]
+    pub controller_frequency: f64,
+    /// distance along the global path from the nearest point to the point whose direction
+    /// is used as the path direction [m]
+    #[serde(default = "default_path_look_ahead_distance")]
+    pub path_look_ahead_distance: f64,
+}
+
+fn default_local_planner() -> DwaPlanner {
+    DwaPlanner::new_from_config_text(DEFAULT_LOCAL_PLANNER_CONFIG)
+        .expect("the bundled DWA planner config must be valid")
+}
+
+fn default_controller_frequency() -> f64 {
+    20.0
+}
+
+fn default_path_look_ahead_distance() -> f64 {
+    1.0
+}
+
+impl Default for NavigationConfig {
+    fn default() -> Self {
+        Self {
+            map: MapConfig::default(),
+            cost_maps: CostMapsConfig::default(),
+            global_planner: GlobalPlannerConfig::default(),
+            local_planner: default_local_planner(),
+            goal_tolerance: GoalTolerance::default(),
+            controller_frequency: default_controller_frequency(),
+            path_look_ahead_distance: default_path_look_ahead_distance(),
+        }
+    }
+}
+
+/// Map used by the navigation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapConfig {
+    /// ROS style map yaml file, which is loaded by
+    /// [`load_ros_occupancy_yaml`](grid_map::utils::load_ros_occupancy_yaml).
+    /// The map is given by the user if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl MapConfig {
+    /// Load the map if the path is given
+    pub fn load(&self) -> Result<Option<GridMap<u8>>, Error> {
+        self.path
+            .as_ref()
+            .map(|path| Ok(grid_map::utils::load_ros_occupancy_yaml(path)?))
+            .transpose()
+    }
+}
+
+/// Cost map layers. The names are used as the cost names of the local planner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct CostMapsConfig {
+    pub path_distance_layer: String,
+    pub goal_distance_layer: String,
+    pub obstacle_distance_layer: String,
+    pub local_goal_distance_layer: String,
+    pub local_goal: LocalGoalMapConfig,
+}
+
+impl Default for CostMapsConfig {
+    fn default() -> Self {
+        Self {
+            path_distance_layer: "path".to_owned(),
+            goal_distance_layer: "goal".to_owned(),
+            obstacle_distance_layer: "obstacle".to_owned(),
+            local_goal_distance_layer: "local_goal".to_owned(),
+            local_goal: LocalGoalMapConfig::default(),
+        }
+    }
+}
+
+/// Global planner and its parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GlobalPlannerConfig {
+    Rrt(RrtConfig),
+}
+
+impl Default for GlobalPlannerConfig {
+    fn default() -> Self {
+        Self::Rrt(RrtConfig::default())
+    }
+}
+
+/// Parameters of RRT-Connect and the path smoothing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RrtConfig {
+    /// [m]
+    pub extend_length: f64,
+    pub max_iterations: usize,
+    pub smooth_iterations: usize,
+}
+
+impl Default for RrtConfig {
+    fn default() -> Self {
+        Self {
+            extend_length: 0.05,
+            max_iterations: 4000,
+            smooth_iterations: 1000,
+        }
+    }
+}
+
+/// The robot reaches the goal if both of the errors are below them
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct GoalTolerance {
+    /// [m]
+    pub distance: f64,
+    /// [rad]
+    pub angle: f64,
+}
+
+impl Default for GoalTolerance {
+    fn default() -> Self {
+        Self {
+            distance: 0.1,
+            angle: 0.4,
+        }
+    }
+}
+
+impl NavigationConfig {
+    /// Load the config file, overridden by the environment variables
+    pub fn new_from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read_to_string(path)?;
+        Self::new_from_text_with_overrides(&source, std::env::vars())
+    }
+
+    /// Parse and validate the config
+    pub fn new_from_text(source: &str) -> Result<Self, Error> {
+        Self::new_from_text_with_overrides(source, [])
+    }
+
+    /// Parse the config overridden by the variables, and validate it
+    ///
+    /// Both of the config and the variables are merged onto the default config, so they can
+    /// change a part of the section which is omitted in the file. The variables whose names
+    /// don't start with [`ENV_OVERRIDE_PREFIX`] are ignored.
+    pub fn new_from_text_with_overrides(
+        source: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, Error> {
+        let mut value = serde_yaml::to_value(Self::default()).map_err(grid_map::Error::from)?;
+        let file: Value = serde_yaml::from_str(source).map_err(grid_map::Error::from)?;
+        merge_value(&mut value, file);
+        apply_overrides(&mut value, vars)?;
+        let config: Self = serde_yaml::from_value(value).map_err(grid_map::Error::from)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the parameters can be used for navigation
+    pub fn validate(&self) -> Result<(), Error> {
+        self.local_planner.validate().map_err(|e| match e {
+            Error::InvalidConfig { field, message } => Error::InvalidConfig {
+                field: format!("local_planner.{field}"),
+                message,
+            },
+            e => e,
+        })?;
+        if self.controller_frequency <= 0.0 || !self.controller_frequency.is_finite() {
+            return Err(Error::invalid_config(
+                "controller_frequency",
+                format!("must be positive, but {}", self.controller_frequency),
+            ));
+        }
+        for (field, value) in [
+            ("goal_tolerance.distance", self.goal_tolerance.distance),
+            ("goal_tolerance.angle", self.goal_tolerance.angle),
+        ] {
+            if value < 0.0 {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must not be negative, but {value}"),
+                ));
+            }
+        }
+        let local_goal = &self.cost_maps.local_goal;
+        if local_goal.look_ahead_distance < 0.0 {
+            return Err(Error::invalid_config(
+                "cost_maps.local_goal.look_ahead_distance",
+                format!(
+                    "must not be negative, but {}",
+                    local_goal.look_ahead_distance
+                ),
+            ));
+        }
+        if self.path_look_ahead_distance < 0.0 {
+            return Err(Error::invalid_config(
+                "path_look_ahead_distance",
+                format!(
+                    "must not be negative, but {}",
+                    self.path_look_ahead_distance
+                ),
+            ));
+        }
+        if let Some(size) = local_goal.window_size {
+            if size <= 0.0 {
+                return Err(Error::invalid_config(
+                    "cost_maps.local_goal.window_size",
+                    format!("must be positive, but {size}"),
+                ));
+            }
+        }
+        match &self.global_planner {
+            GlobalPlannerConfig::Rrt(rrt) => {
+                if rrt.extend_length <= 0.0 {
+                    return Err(Error::invalid_config(
+                        "global_planner.extend_length",
+                        format!("must be positive, but {}", rrt.extend_length),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Period of the control loop
+    pub fn controller_period(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(1.0 / self.controller_frequency)
+    }
+
+    /// Replace the `local_planner` section of the config text, keeping the other sections
+    pub fn replace_local_planner_text(source: &str, planner: &DwaPlanner) -> Result<String, Error> {
+        let mut value: Value = serde_yaml::from_str(source).map_err(grid_map::Error::from)?;
+        if value.is_null() {
+            value = Value::Mapping(Default::default());
+        }
+        let Value::Mapping(sections) = &mut value else {
+            return Err(Error::Other(
+                "the navigation config must be a mapping".to_owned(),
+            ));
+        };
+        sections.insert(
+            "local_planner".into(),
+            serde_yaml::to_value(planner).map_err(grid_map::Error::from)?,
+        );
+        Ok(serde_yaml::to_string(&value).map_err(grid_map::Error::from)?)
+    }
+
+    /// Write the planner to the `local_planner` section of the config file
+    ///
+    /// The other sections are kept, but the comments in the file are not.
+    pub fn save_local_planner(path: impl AsRef<Path>, planner: &DwaPlanner) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = Self::replace_local_planner_text(&fs::read_to_string(path)?, planner)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Overwrite the fields of `base` by the ones of `value`
+///
+/// A mapping with another `type` replaces the whole mapping since it is another variant of the
+/// enum, and an empty (null) section keeps the default.
+fn merge_value(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Mapping(base), Value::Mapping(value))
+            if base.get("type") == value.get("type") || value.get("type").is_none() =>
+        {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(base) => merge_value(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Mapping(_), Value::Null) => {}
+        (base, value) => *base = value,
+    }
+}
+
+/// Overwrite the values by the variables like `OPENRR_NAV__SECTION__FIELD=value`
+///
+/// The value is parsed as YAML, so `0.2`, `true` and `[0.5, 1.0]` are available.
+fn apply_overrides(
+    config: &mut Value,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), Error> {
+    let mut vars = vars
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(ENV_OVERRIDE_PREFIX))
+        .collect::<Vec<_>>();
+    // apply in the same order regardless of the order of the environment variables
+    vars.sort();
+    for (key, value) in vars {
+        let keys = key[ENV_OVERRIDE_PREFIX.len()..]
+            .split("__")
+            .map(|k| k.to_lowercase())
+            .collect::<Vec<_>>();
+        if keys.iter().any(|k| k.is_empty()) {
+            return Err(Error::invalid_config(key, "empty field name"));
+        }
+        let value: Value = serde_yaml::from_str(&value).map_err(grid_map::Error::from)?;
+        let mut target = &mut *config;
+        for k in &keys {
+            let Value::Mapping(mapping) = target else {
+                return Err(Error::invalid_config(key, "the parent is not a mapping"));
+            };
+            target = mapping
+                .entry(Value::String(k.clone()))
+                .or_insert_with(|| Value::Mapping(Default::default()));
+        }
+        *target = value;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_test() {
+        let config = NavigationConfig::new_from_text("").unwrap();
+        assert_eq!(config.goal_tolerance, GoalTolerance::default());
+        assert_eq!(config.controller_frequency, 20.0);
+        assert_eq!(config.path_look_ahead_distance, 1.0);
+        assert_eq!(config.local_planner.controller_dt(), 0.1);
+        assert!(config.map.load().unwrap().is_none());
+
+        let config = NavigationConfig::new_from_file("config/navigation_config.yaml")
+            .unwrap_or_else(|e| {
+                panic!("{e}");
+            });
+        assert_eq!(
+            config.global_planner,
+            GlobalPlannerConfig::Rrt(RrtConfig::default())
+        );
+        assert_eq!(config.cost_maps, CostMapsConfig::default());
+
+        // same orientation as the other loaders of the ROS map
+        let map = MapConfig {
+            path: Some("config/benchmark/room.yaml".into()),
+        }
+        .load()
+        .unwrap()
+        .unwrap();
+        let expected = grid_map::utils::load_ros_occupancy_yaml("config/benchmark/room.yaml");
+        assert_eq!(map.cells(), expected.unwrap().cells());
+    }
+
+    #[test]
+    fn navigation_config_test() {
+        let config = NavigationConfig::new_from_text(
+            r#"
+global_planner:
+  type: rrt
+  extend_length: 0.1
+goal_tolerance:
+  distance: 0.2
+controller_frequency: 10.0
+"#,
+        )
+        .unwrap();
+        let GlobalPlannerConfig::Rrt(rrt) = &config.global_planner;
+        assert_eq!(rrt.extend_length, 0.1);
+        assert_eq!(rrt.max_iterations, 4000);
+        assert_eq!(config.goal_tolerance.distance, 0.2);
+        assert_eq!(config.goal_tolerance.angle, 0.4);
+        assert_eq!(config.controller_period().as_millis(), 100);
+        assert_eq!(config.cost_maps.local_goal, LocalGoalMapConfig::default());
+
+        assert!(NavigationConfig::new_from_text("goal_tolerance:\n  foo: 1.0").is_err());
+        assert!(NavigationConfig::new_from_text("unknown_section: 1").is_err());
+        assert!(matches!(
+            NavigationConfig::new_from_text("controller_frequency: 0.0"),
+            Err(Error::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn replace_local_planner_text_test() {
+        let source = "goal_tolerance:\n  distance: 0.2\ncontroller_frequency: 10.0\n";
+        let mut planner = NavigationConfig::default().local_planner;
+        planner
+            .map_name_weight_mut()
+            .insert("velocity".to_owned(), 2.5);
+        let text = NavigationConfig::replace_local_planner_text(source, &planner).unwrap();
+        let config = NavigationConfig::new_from_text(&text).unwrap();
+        assert_eq!(config.local_planner.map_name_weight()["velocity"], 2.5);
+        assert_eq!(config.goal_tolerance.distance, 0.2);
+        assert_eq!(config.controller_frequency, 10.0);
+
+        let text = NavigationConfig::replace_local_planner_text("", &planner).unwrap();
+        let config = NavigationConfig::new_from_text(&text).unwrap();
+        assert_eq!(config.local_planner.map_name_weight()["velocity"], 2.5);
+        assert!(NavigationConfig::replace_local_planner_text("1.0", &planner).is_err());
+    }
+
+    #[test]
+    fn env_override_test() {
+        let vars = [
+            ("OPENRR_NAV__GOAL_TOLERANCE__DISTANCE", "0.3"),
+            ("OPENRR_NAV__LOCAL_PLANNER__NUM_VEL_SAMPLE", "7"),
+            ("OPENRR_NAV__MAP__PATH", "map.yaml"),
+            ("OTHER__CONTROLLER_FREQUENCY", "1.0"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let source = r#"
+goal_tolerance:
+  distance: 0.2
+local_planner:
+  limits:
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [0.0, -2.0]
+    min_acceleration: [-2.0, -5.0]
+  cost_name_weight: []
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 5
+"#;
+        let config = NavigationConfig::new_from_text_with_overrides(source, vars).unwrap();
+        assert_eq!(config.goal_tolerance.distance, 0.3);
+        assert_eq!(config.local_planner.num_vel_sample(), 7);
+        assert_eq!(config.map.path, Some(PathBuf::from("map.yaml")));
+        assert_eq!(config.controller_frequency, 20.0);
+
+        // overrides are validated too
+        let vars = [("OPENRR_NAV__LOCAL_PLANNER__NUM_VEL_SAMPLE", "0")]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert!(matches!(
+            NavigationConfig::new_from_text_with_overrides(source, vars),
+            Err(Error::InvalidConfig { field, .. }) if field == "local_planner.num_vel_sample"
+        ));
+        // the weight of a custom critic is checked after the critic is registered
+        let config = NavigationConfig::new_from_text(&source.replace(
+            "cost_name_weight: []",
+            "cost_name_weight:\n    - name: custom\n      value: 1.0",
+        ))
+        .unwrap();
+        assert!(config.local_planner.validate_critics().is_err());
+        // the section omitted in the file
+        let vars = [
+            ("OPENRR_NAV__LOCAL_PLANNER__NUM_VEL_SAMPLE", "7"),
+            ("OPENRR_NAV__COST_MAPS__LOCAL_GOAL__WINDOW_SIZE", "3.0"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let config = NavigationConfig::new_from_text_with_overrides("", vars).unwrap();
+        assert_eq!(config.local_planner.num_vel_sample(), 7);
+        assert_eq!(config.local_planner.controller_dt(), 0.1);
+        assert_eq!(config.cost_maps.local_goal.window_size, Some(3.0));
+        assert_eq!(config.cost_maps.local_goal.look_ahead_distance, 1.0);
+        // another variant of the enum replaces the section
+        let config = NavigationConfig::new_from_text(
+            "local_planner:\n  motion_model:\n    type: ackermann\n    wheel_base: 0.5\n    min_turning_radius: 1.0\n    max_steering_rate: 0.5",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.local_planner.motion_model(),
+            crate::MotionModel::Ackermann(_)
+        ));
+        // unknown field
+        let vars =
+            [("OPENRR_NAV__GOAL_TOLERANCE__FOO", "1.0")].map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert!(NavigationConfig::new_from_text_with_overrides("", vars).is_err());
+        // can't go into a scalar
+        let vars = [("OPENRR_NAV__CONTROLLER_FREQUENCY__FOO", "1.0")]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert!(matches!(
+            NavigationConfig::new_from_text_with_overrides("controller_frequency: 1.0", vars),
+            Err(Error::InvalidConfig { .. })
+        ));
+    }
+}
@@ -0,0 +1,358 @@
+use grid_map::{Cell, Grid, GridMap, Position, Size};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, LaserScan, Pose, Result};
+
+/// Parameters of [`ObstacleLayer`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ObstacleLayerConfig {
+    /// hits farther than it from the sensor are not marked [m]
+    pub max_marking_range: f64,
+    /// free space farther than it from the sensor is not cleared [m]
+    pub max_clearing_range: f64,
+    /// obstacles which have not been marked for it are removed [s].
+    /// The obstacles are kept until cleared if omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decay_time: Option<f64>,
+}
+
+impl Default for ObstacleLayerConfig {
+    fn default() -> Self {
+        Self {
+            max_marking_range: 2.5,
+            max_clearing_range: 3.0,
+            decay_time: None,
+        }
+    }
+}
+
+impl ObstacleLayerConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (field, value) in [
+            ("max_marking_range", self.max_marking_range),
+            ("max_clearing_range", self.max_clearing_range),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be positive, but {value}"),
+                ));
+            }
+        }
+        if let Some(decay_time) = self.decay_time {
+            if !(decay_time.is_finite() && decay_time > 0.0) {
+                return Err(Error::invalid_config(
+                    "decay_time",
+                    format!("must be positive, but {decay_time}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Layer of the obstacles observed by the sensors
+///
+/// The hits are marked as `Cell::Obstacle`, and the obstacles between the sensor and the hits
+/// are cleared. The other cells are `Cell::Uninitialized`.
+#[derive(Debug, Clone)]
+pub struct ObstacleLayer {
+    config: ObstacleLayerConfig,
+    map: GridMap<u8>,
+    /// time when the cell was marked last
+    marked_times: Vec<Option<f64>>,
+}
+
+impl ObstacleLayer {
+    pub fn new(
+        min_point: Position,
+        max_point: Position,
+        resolution: f64,
+        config: ObstacleLayerConfig,
+    ) -> Result<Self> {
+        config.validate()?;
+        let map = GridMap::new(min_point, max_point, resolution);
+        Ok(Self {
+            marked_times: vec![None; map.len()],
+            map,
+            config,
+        })
+    }
+
+    /// Create the layer which has the same cells as the map
+    pub fn new_aligned_to(map: &GridMap<u8>, config: ObstacleLayerConfig) -> Result<Self> {
+        config.validate()?;
+        let map = GridMap::new_with_size(
+            map.min_point().to_owned(),
+            Size::new(map.width(), map.height()),
+            map.resolution(),
+        );
+        Ok(Self {
+            marked_times: vec![None; map.len()],
+            map,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &ObstacleLayerConfig {
+        &self.config
+    }
+
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    pub fn is_obstacle(&self, grid: &Grid) -> bool {
+        self.map.cell(grid).is_some_and(|c| c.is_obstacle())
+    }
+
+    /// Remove all the obstacles
+    pub fn clear(&mut self) {
+        self.map.cells_mut().fill(Cell::Uninitialized);
+        self.marked_times.fill(None);
+    }
+
+    /// Update the layer by the hit points observed from the sensor at `time` [s]
+    ///
+    /// All the rays are cleared before marking, so a hit is not cleared by the other rays of
+    /// the same observation. The obstacles are decayed by `time` first.
+    pub fn insert_points(
+        &mut self,
+        sensor: &Position,
+        hits: impl IntoIterator<Item = Position>,
+        time: f64,
+    ) {
+        self.decay(time);
+        let mut marks = vec![];
+        for hit in hits {
+            let range = ((hit.x - sensor.x).powi(2) + (hit.y - sensor.y).powi(2)).sqrt();
+            if !range.is_finite() {
+                continue;
+            }
+            self.clear_ray(sensor, &hit, range, true);
+            if range <= self.config.max_marking_range {
+                marks.push(hit);
+            }
+        }
+        for hit in marks {
+            if let Some(grid) = self.map.to_grid(hit.x, hit.y) {
+                self.map.set_obstacle(&grid);
+                self.marked_times[grid.y * self.map.width() + grid.x] = Some(time);
+            }
+        }
+    }
+
+    /// Update the layer by the laser scan from the sensor pose at `time` [s]
+    ///
+    /// The beams without return, which are infinity or not less than `range_max`, clear the cells
+    /// up to `max_clearing_range`. `NaN` and too short ranges are ignored.
+    pub fn insert_scan(&mut self, scan: &LaserScan, sensor_pose: &Pose, time: f64) {
+        let sensor = Position::new(sensor_pose.translation.x, sensor_pose.translation.y);
+        let yaw = sensor_pose.rotation.angle();
+        for (i, range) in scan.ranges.iter().enumerate() {
+            if range.is_nan() || *range < scan.range_max {
+                continue;
+            }
+            let angle = yaw + scan.angle(i);
+            let end = Position::new(sensor.x + angle.cos(), sensor.y + angle.sin());
+            self.clear_ray(&sensor, &end, f64::INFINITY, false);
+        }
+        let hits = scan
+            .points_in(sensor_pose)
+            .into_iter()
+            .map(|p| Position::new(p.x, p.y))
+            .collect::<Vec<_>>();
+        self.insert_points(&sensor, hits, time);
+    }
+
+    /// Remove the obstacles which have not been marked for `decay_time` until `time` [s]
+    pub fn decay(&mut self, time: f64) {
+        let Some(decay_time) = self.config.decay_time else {
+            return;
+        };
+        for (cell, marked_time) in self.map.cells_mut().iter_mut().zip(&mut self.marked_times) {
+            if matches!(marked_time, Some(t) if time - *t > decay_time) {
+                *cell = Cell::Uninitialized;
+                *marked_time = None;
+            }
+        }
+    }
+
+    /// Copy of the map with the obstacles of the layer
+    ///
+    /// The cells of the map which are out of the layer are kept.
+    pub fn merged_with(&self, map: &GridMap<u8>) -> GridMap<u8> {
+        let mut merged = map.clone();
+        for (index, cell) in self.map.cells().iter().enumerate() {
+            if !cell.is_obstacle() {
+                continue;
+            }
+            let grid = Grid::new(index % self.map.width(), index / self.map.width());
+            let Some(position) = self.map.to_position(&grid) else {
+                continue;
+            };
+            if let Some(grid) = merged.to_grid(position.x, position.y) {
+                merged.set_obstacle(&grid);
+            }
+        }
+        merged
+    }
+
+    /// Clear the obstacles on the ray toward `end` up to `range` and `max_clearing_range`
+    fn clear_ray(&mut self, sensor: &Position, end: &Position, range: f64, keep_end: bool) {
+        let clearing_range = range.min(self.config.max_clearing_range);
+        let end_grid = self.map.to_grid(end.x, end.y);
+        let keep_end = keep_end && range <= self.config.max_clearing_range;
+        let scale =
+            clearing_range / ((end.x - sensor.x).powi(2) + (end.y - sensor.y).powi(2)).sqrt();
+        if !scale.is_finite() {
+            return;
+        }
+        let end = Position::new(
+            sensor.x + (end.x - sensor.x) * scale,
+            sensor.y + (end.y - sensor.y) * scale,
+        );
+        for grid in self.map.raytrace(sensor, &end) {
+            if keep_end && Some(grid) == end_grid {
+                continue;
+            }
+            let index = grid.y * self.map.width() + grid.x;
+            if self.marked_times[index].is_some() {
+                self.map.cells_mut()[index] = Cell::Uninitialized;
+                self.marked_times[index] = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+
+    fn new_layer(config: ObstacleLayerConfig) -> ObstacleLayer {
+        ObstacleLayer::new(
+            Position::new(0.0, 0.0),
+            Position::new(3.0, 1.0),
+            0.1,
+            config,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn obstacle_layer_test() {
+        let mut layer = new_layer(ObstacleLayerConfig {
+            max_marking_range: 2.0,
+            max_clearing_range: 2.5,
+            decay_time: None,
+        });
+        let sensor = Position::new(0.05, 0.55);
+        layer.insert_points(
+            &sensor,
+            [Position::new(1.05, 0.55), Position::new(1.05, 0.45)],
+            0.0,
+        );
+        // not cleared by the other ray
+        assert!(layer.is_obstacle(&Grid::new(10, 5)));
+        assert!(layer.is_obstacle(&Grid::new(10, 4)));
+
+        // the obstacle has moved
+        layer.insert_points(&sensor, [Position::new(1.55, 0.55)], 1.0);
+        assert!(!layer.is_obstacle(&Grid::new(10, 5)));
+        assert!(layer.is_obstacle(&Grid::new(10, 4)));
+        assert!(layer.is_obstacle(&Grid::new(15, 5)));
+
+        // beyond the marking range, but cleared within the clearing range
+        layer.insert_points(&sensor, [Position::new(2.35, 0.55)], 2.0);
+        assert!(!layer.is_obstacle(&Grid::new(15, 5)));
+        assert!(!layer.is_obstacle(&Grid::new(23, 5)));
+
+        // beyond the clearing range
+        layer.insert_points(&Position::new(0.95, 0.55), [Position::new(2.75, 0.55)], 3.0);
+        assert!(layer.is_obstacle(&Grid::new(27, 5)));
+        layer.insert_points(&sensor, [Position::new(2.95, 0.55)], 4.0);
+        assert!(layer.is_obstacle(&Grid::new(27, 5)));
+        assert!(!layer.is_obstacle(&Grid::new(29, 5)));
+
+        layer.clear();
+        assert!(layer.map().cells().iter().all(|c| c.is_uninitialized()));
+    }
+
+    #[test]
+    fn obstacle_layer_decay_test() {
+        let mut layer = new_layer(ObstacleLayerConfig {
+            decay_time: Some(1.0),
+            ..Default::default()
+        });
+        let sensor = Position::new(0.05, 0.55);
+        layer.insert_points(&sensor, [Position::new(1.05, 0.55)], 0.0);
+        layer.insert_points(&sensor, [Position::new(1.05, 0.15)], 0.5);
+        layer.decay(1.2);
+        assert!(!layer.is_obstacle(&Grid::new(10, 5)));
+        assert!(layer.is_obstacle(&Grid::new(10, 1)));
+        // marked again
+        layer.insert_points(&sensor, [Position::new(1.05, 0.15)], 1.4);
+        layer.decay(2.0);
+        assert!(layer.is_obstacle(&Grid::new(10, 1)));
+        layer.decay(2.5);
+        assert!(!layer.is_obstacle(&Grid::new(10, 1)));
+    }
+
+    #[test]
+    fn obstacle_layer_scan_test() {
+        let mut static_map = GridMap::new(Position::new(0.0, 0.0), Position::new(3.0, 1.0), 0.1);
+        static_map.set_obstacle(&Grid::new(29, 0)).unwrap();
+        let mut layer = ObstacleLayer::new_aligned_to(&static_map, Default::default()).unwrap();
+        let pose = Pose::new(na::Vector2::new(0.55, 0.55), std::f64::consts::FRAC_PI_2);
+        let mut scan = LaserScan {
+            angle_min: -std::f64::consts::FRAC_PI_2,
+            angle_increment: std::f64::consts::FRAC_PI_2,
+            range_min: 0.1,
+            range_max: 5.0,
+            ranges: vec![1.0, 0.3, f64::NAN],
+        };
+        layer.insert_scan(&scan, &pose, 0.0);
+        assert!(layer.is_obstacle(&Grid::new(15, 5)));
+        assert!(layer.is_obstacle(&Grid::new(5, 8)));
+        assert_eq!(
+            layer
+                .map()
+                .cells()
+                .iter()
+                .filter(|c| c.is_obstacle())
+                .count(),
+            2
+        );
+
+        let merged = layer.merged_with(&static_map);
+        assert!(merged.cell(&Grid::new(15, 5)).unwrap().is_obstacle());
+        assert!(merged.cell(&Grid::new(29, 0)).unwrap().is_obstacle());
+        assert!(!static_map.cell(&Grid::new(15, 5)).unwrap().is_obstacle());
+
+        // no return clears the obstacle
+        scan.ranges[0] = f64::INFINITY;
+        layer.insert_scan(&scan, &pose, 1.0);
+        assert!(!layer.is_obstacle(&Grid::new(15, 5)));
+        assert!(layer.is_obstacle(&Grid::new(5, 8)));
+    }
+
+    #[test]
+    fn obstacle_layer_config_test() {
+        let config = ObstacleLayerConfig {
+            decay_time: Some(0.0),
+            ..Default::default()
+        };
+        assert!(ObstacleLayer::new(
+            Position::new(0.0, 0.0),
+            Position::new(1.0, 1.0),
+            0.1,
+            config
+        )
+        .is_err());
+        let config: ObstacleLayerConfig = serde_yaml::from_str("decay_time: 2.0").unwrap();
+        assert_eq!(config.decay_time, Some(2.0));
+        assert_eq!(config.max_marking_range, 2.5);
+    }
+}
@@ -0,0 +1,394 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::{integrate_velocity, normalize_angle, to_arci_pose},
+    Error, Pose, Result, Velocity,
+};
+
+/// Parameters of [`PoseEstimator`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PoseEstimatorConfig {
+    /// distance between the left and the right wheels [m]
+    pub wheel_separation: f64,
+    /// standard deviation of the velocity measured by the wheels [x, y, theta]
+    pub velocity_std_dev: [f64; 3],
+    /// standard deviation of the yaw rate measured by the IMU [rad/s]
+    pub yaw_rate_std_dev: f64,
+    /// standard deviation of the initial pose [x, y, theta]
+    pub initial_std_dev: [f64; 3],
+    /// corrections whose Mahalanobis distance is larger than it are rejected.
+    /// All the corrections are accepted if omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_mahalanobis_distance: Option<f64>,
+}
+
+impl Default for PoseEstimatorConfig {
+    fn default() -> Self {
+        Self {
+            wheel_separation: 0.5,
+            velocity_std_dev: [0.05, 0.05, 0.1],
+            yaw_rate_std_dev: 0.02,
+            initial_std_dev: [0.1, 0.1, 0.1],
+            max_mahalanobis_distance: None,
+        }
+    }
+}
+
+impl PoseEstimatorConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.wheel_separation.is_finite() && self.wheel_separation > 0.0) {
+            return Err(Error::invalid_config(
+                "wheel_separation",
+                format!("must be positive, but {}", self.wheel_separation),
+            ));
+        }
+        for (field, values) in [
+            ("velocity_std_dev", &self.velocity_std_dev[..]),
+            ("yaw_rate_std_dev", &[self.yaw_rate_std_dev][..]),
+            ("initial_std_dev", &self.initial_std_dev[..]),
+        ] {
+            if values.iter().any(|v| !(v.is_finite() && *v >= 0.0)) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be non-negative, but {values:?}"),
+                ));
+            }
+        }
+        if let Some(distance) = self.max_mahalanobis_distance {
+            if !(distance.is_finite() && distance > 0.0) {
+                return Err(Error::invalid_config(
+                    "max_mahalanobis_distance",
+                    format!("must be positive, but {distance}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Dead-reckoning by the wheels or the IMU, fused with the absolute poses by an EKF
+///
+/// The odometry pose is integrated from the identity without corrections, which is continuous
+/// but drifts. The map pose is corrected by the localizer such as [`Amcl`](crate::Amcl).
+/// [`arci::Localization`] returns the map pose.
+#[derive(Debug, Clone)]
+pub struct PoseEstimator {
+    config: PoseEstimatorConfig,
+    odometry_pose: Pose,
+    map_pose: Pose,
+    covariance: na::Matrix3<f64>,
+}
+
+impl PoseEstimator {
+    pub fn new(initial_pose: &Pose, config: PoseEstimatorConfig) -> Result<Self> {
+        config.validate()?;
+        let covariance = initial_covariance(&config);
+        Ok(Self {
+            config,
+            odometry_pose: Pose::identity(),
+            map_pose: *initial_pose,
+            covariance,
+        })
+    }
+
+    pub fn config(&self) -> &PoseEstimatorConfig {
+        &self.config
+    }
+
+    /// Pose in the odometry frame
+    pub fn odometry_pose(&self) -> Pose {
+        self.odometry_pose
+    }
+
+    /// Pose in the map frame
+    pub fn map_pose(&self) -> Pose {
+        self.map_pose
+    }
+
+    /// Covariance of the map pose [x, y, theta]
+    pub fn covariance(&self) -> &na::Matrix3<f64> {
+        &self.covariance
+    }
+
+    /// Transform from the map frame to the odometry frame
+    ///
+    /// `map_to_odom() * odometry_pose() == map_pose()`
+    pub fn map_to_odom(&self) -> Pose {
+        self.map_pose * self.odometry_pose.inverse()
+    }
+
+    /// Set the map pose with the initial covariance, keeping the odometry pose
+    pub fn reset(&mut self, map_pose: &Pose) {
+        self.map_pose = *map_pose;
+        self.covariance = initial_covariance(&self.config);
+    }
+
+    /// Integrate the velocity in the robot frame measured by the wheels for `dt` [s]
+    pub fn update_velocity(&mut self, velocity: &Velocity, dt: f64) {
+        let std_dev = self.config.velocity_std_dev;
+        self.predict(velocity, std_dev, dt);
+    }
+
+    /// Integrate the velocities of the left and the right wheels [m/s] for `dt` [s]
+    pub fn update_wheel_velocities(&mut self, left: f64, right: f64, dt: f64) {
+        let velocity = Velocity {
+            x: (left + right) / 2.0,
+            y: 0.0,
+            theta: (right - left) / self.config.wheel_separation,
+        };
+        self.update_velocity(&velocity, dt);
+    }
+
+    /// Integrate the linear velocity of the wheels and the yaw rate of the IMU for `dt` [s]
+    pub fn update_yaw_rate(&mut self, linear_velocity: f64, yaw_rate: f64, dt: f64) {
+        let velocity = Velocity {
+            x: linear_velocity,
+            y: 0.0,
+            theta: yaw_rate,
+        };
+        let std_dev = [
+            self.config.velocity_std_dev[0],
+            self.config.velocity_std_dev[1],
+            self.config.yaw_rate_std_dev,
+        ];
+        self.predict(&velocity, std_dev, dt);
+    }
+
+    /// Fuse the absolute pose in the map frame with its covariance [x, y, theta]
+    ///
+    /// Returns false if the correction is rejected by `max_mahalanobis_distance`.
+    pub fn correct(&mut self, pose: &Pose, covariance: &na::Matrix3<f64>) -> bool {
+        let innovation = na::Vector3::new(
+            pose.translation.x - self.map_pose.translation.x,
+            pose.translation.y - self.map_pose.translation.y,
+            normalize_angle(pose.rotation.angle() - self.map_pose.rotation.angle()),
+        );
+        let innovation_covariance = self.covariance + covariance;
+        let Some(inverse) = innovation_covariance.try_inverse() else {
+            return false;
+        };
+        if let Some(max_distance) = self.config.max_mahalanobis_distance {
+            let squared = (innovation.transpose() * inverse * innovation)[0];
+            if squared > max_distance.powi(2) {
+                return false;
+            }
+        }
+        let gain = self.covariance * inverse;
+        let delta = gain * innovation;
+        self.map_pose = Pose::new(
+            self.map_pose.translation.vector + delta.fixed_rows::<2>(0),
+            self.map_pose.rotation.angle() + delta[2],
+        );
+        // Joseph form to keep the covariance symmetric and positive definite
+        let i_kh = na::Matrix3::identity() - gain;
+        self.covariance =
+            i_kh * self.covariance * i_kh.transpose() + gain * covariance * gain.transpose();
+        true
+    }
+
+    fn predict(&mut self, velocity: &Velocity, std_dev: [f64; 3], dt: f64) {
+        let motion = integrate_velocity(velocity, dt);
+        self.odometry_pose *= motion;
+
+        let angle = self.map_pose.rotation.angle();
+        let (sin, cos) = angle.sin_cos();
+        let (dx, dy) = (motion.translation.x, motion.translation.y);
+        let jacobian = na::Matrix3::new(
+            1.0,
+            0.0,
+            -sin * dx - cos * dy,
+            0.0,
+            1.0,
+            cos * dx - sin * dy,
+            0.0,
+            0.0,
+            1.0,
+        );
+        // the velocity noise in the map frame
+        let rotation = na::Matrix3::new(cos, -sin, 0.0, sin, cos, 0.0, 0.0, 0.0, 1.0) * dt;
+        let noise = na::Matrix3::from_diagonal(&na::Vector3::from(std_dev.map(|s| s * s)));
+        self.covariance = jacobian * self.covariance * jacobian.transpose()
+            + rotation * noise * rotation.transpose();
+        self.map_pose *= motion;
+    }
+}
+
+fn initial_covariance(config: &PoseEstimatorConfig) -> na::Matrix3<f64> {
+    na::Matrix3::from_diagonal(&na::Vector3::from(config.initial_std_dev.map(|s| s * s)))
+}
+
+impl arci::Localization for PoseEstimator {
+    fn current_pose(
+        &self,
+        _frame_id: &str,
+    ) -> std::result::Result<arci::Isometry2<f64>, arci::Error> {
+        Ok(to_arci_pose(&self.map_pose))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RobotSimulator, RobotSimulatorConfig};
+    use arci::{Localization, MoveBase};
+    use grid_map::{GridMap, Position};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn dead_reckoning_test() {
+        let initial = Pose::new(na::Vector2::new(1.0, 2.0), 0.5);
+        let mut estimator = PoseEstimator::new(&initial, Default::default()).unwrap();
+        // turn left along the circle of the radius 1 by the half
+        for _ in 0..100 {
+            estimator.update_wheel_velocities(0.75, 1.25, std::f64::consts::PI / 100.0);
+        }
+        let odometry = estimator.odometry_pose();
+        assert!(odometry.translation.x.abs() < 1e-9);
+        assert!((odometry.translation.y - 2.0).abs() < 1e-9);
+        assert!((odometry.rotation.angle().abs() - std::f64::consts::PI).abs() < 1e-9);
+        // no correction
+        assert!(
+            (estimator.map_to_odom().to_homogeneous() - initial.to_homogeneous()).norm() < 1e-9
+        );
+        let map_pose = estimator.map_pose();
+        assert!((map_pose.to_homogeneous() - (initial * odometry).to_homogeneous()).norm() < 1e-9);
+        let pose = estimator.current_pose("").unwrap();
+        assert!((pose.translation.x - map_pose.translation.x).abs() < 1e-9);
+        // the uncertainty grows
+        assert!(estimator.covariance()[(0, 0)] > 0.01);
+
+        // the IMU, same motion with the omni model
+        let mut estimator = PoseEstimator::new(&Pose::identity(), Default::default()).unwrap();
+        let mut omni = estimator.clone();
+        for _ in 0..10 {
+            estimator.update_yaw_rate(0.5, 1.0, 0.1);
+            omni.update_velocity(
+                &Velocity {
+                    x: 0.0,
+                    y: 0.5,
+                    theta: 1.0,
+                },
+                0.1,
+            );
+        }
+        let pose = estimator.odometry_pose();
+        assert!((pose.translation.x - 0.5 * 1.0_f64.sin()).abs() < 1e-9);
+        assert!((pose.translation.y - 0.5 * (1.0 - 1.0_f64.cos())).abs() < 1e-9);
+        let omni = omni.odometry_pose();
+        assert!((omni.translation.x + 0.5 * (1.0 - 1.0_f64.cos())).abs() < 1e-9);
+        assert!((omni.translation.y - 0.5 * 1.0_f64.sin()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn correction_test() {
+        let mut estimator = PoseEstimator::new(
+            &Pose::identity(),
+            PoseEstimatorConfig {
+                max_mahalanobis_distance: Some(5.0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let measurement = Pose::new(na::Vector2::new(0.1, 0.0), 0.0);
+        // same uncertainty as the estimate
+        let covariance = *estimator.covariance();
+        assert!(estimator.correct(&measurement, &covariance));
+        assert!((estimator.map_pose().translation.x - 0.05).abs() < 1e-9);
+        assert!((estimator.covariance() - covariance / 2.0).norm() < 1e-9);
+        // the odometry frame follows the correction
+        assert!((estimator.map_to_odom().translation.x - 0.05).abs() < 1e-9);
+        assert_eq!(estimator.odometry_pose(), Pose::identity());
+
+        // outlier
+        let far = Pose::new(na::Vector2::new(5.0, 0.0), 0.0);
+        assert!(!estimator.correct(&far, &covariance));
+        assert!((estimator.map_pose().translation.x - 0.05).abs() < 1e-9);
+
+        // angle across pi
+        estimator.reset(&Pose::new(na::Vector2::new(0.0, 0.0), 3.1));
+        assert!(estimator.correct(
+            &Pose::new(na::Vector2::new(0.0, 0.0), -3.1),
+            &estimator.covariance().clone()
+        ));
+        let angle = estimator.map_pose().rotation.angle();
+        assert!((angle.abs() - std::f64::consts::PI).abs() < 1e-6, "{angle}");
+    }
+
+    #[test]
+    fn fusion_test() {
+        let map = GridMap::new(Position::new(-10.0, -10.0), Position::new(10.0, 10.0), 0.1);
+        let simulator = RobotSimulator::new(
+            map,
+            Pose::identity(),
+            RobotSimulatorConfig {
+                slip_std_dev: 0.1,
+                seed: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut estimator = PoseEstimator::new(&Pose::identity(), Default::default()).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let localizer_std_dev: f64 = 0.1;
+        let covariance = na::Matrix3::from_diagonal_element(localizer_std_dev.powi(2));
+        simulator
+            .send_velocity(&arci::BaseVelocity::new(0.8, 0.0, 0.4))
+            .unwrap();
+        let mut max_error = 0.0_f64;
+        for i in 1..=400 {
+            simulator.step(0.05);
+            // the yaw rate of the wheels is not calibrated
+            let velocity = simulator.velocity();
+            estimator.update_velocity(
+                &Velocity {
+                    theta: velocity.theta * 1.1,
+                    ..velocity
+                },
+                0.05,
+            );
+            if i % 20 == 0 {
+                let truth = simulator.pose();
+                let measurement = Pose::new(
+                    truth.translation.vector
+                        + na::Vector2::new(
+                            crate::utils::sample_normal(&mut rng, localizer_std_dev),
+                            crate::utils::sample_normal(&mut rng, localizer_std_dev),
+                        ),
+                    truth.rotation.angle()
+                        + crate::utils::sample_normal(&mut rng, localizer_std_dev),
+                );
+                assert!(estimator.correct(&measurement, &covariance));
+            }
+            let error = (estimator.map_pose().translation.vector
+                - simulator.pose().translation.vector)
+                .norm();
+            max_error = max_error.max(error);
+        }
+        // the odometry frame starts at the identity same as the map frame
+        let odometry_error = (estimator.odometry_pose().translation.vector
+            - simulator.pose().translation.vector)
+            .norm();
+        assert!(max_error < 0.3, "{max_error}");
+        assert!(odometry_error > 1.0, "{odometry_error}");
+    }
+
+    #[test]
+    fn pose_estimator_config_test() {
+        let config = PoseEstimatorConfig {
+            wheel_separation: 0.0,
+            ..Default::default()
+        };
+        assert!(PoseEstimator::new(&Pose::identity(), config).is_err());
+        let config = PoseEstimatorConfig {
+            velocity_std_dev: [0.1, -0.1, 0.1],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config: PoseEstimatorConfig =
+            serde_yaml::from_str("max_mahalanobis_distance: 3.0").unwrap();
+        assert_eq!(config.max_mahalanobis_distance, Some(3.0));
+        assert_eq!(config.wheel_separation, 0.5);
+    }
+}
@@ -0,0 +1,438 @@
+use std::sync::Mutex;
+
+use grid_map::{Cell, Grid, GridMap, Position};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::{integrate_velocity, sample_normal, to_arci_pose},
+    Error, Pose, Result, Velocity,
+};
+
+/// Parameters of [`RobotSimulator`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RobotSimulatorConfig {
+    /// maximum velocity [x, theta], the commands are clamped by it
+    pub max_velocity: [f64; 2],
+    /// maximum acceleration [x, theta]
+    pub max_acceleration: [f64; 2],
+    /// standard deviation of the slip, the ratio to the velocity
+    pub slip_std_dev: f64,
+    /// radius of the robot for the collision check [m]
+    pub robot_radius: f64,
+    /// seed of the random number generator, which is random if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for RobotSimulatorConfig {
+    fn default() -> Self {
+        Self {
+            max_velocity: [1.0, 2.0],
+            max_acceleration: [2.0, 5.0],
+            slip_std_dev: 0.0,
+            robot_radius: 0.2,
+            seed: None,
+        }
+    }
+}
+
+impl RobotSimulatorConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (field, values) in [
+            ("max_velocity", self.max_velocity),
+            ("max_acceleration", self.max_acceleration),
+        ] {
+            if !values.iter().all(|v| v.is_finite() && *v > 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be positive, but {values:?}"),
+                ));
+            }
+        }
+        for (field, value) in [
+            ("slip_std_dev", self.slip_std_dev),
+            ("robot_radius", self.robot_radius),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be non-negative, but {value}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct SimulatorState {
+    pose: Pose,
+    /// pose integrated from the velocity without the slip, as the wheel odometry
+    odometry: Pose,
+    velocity: Velocity,
+    command: Velocity,
+    collided: bool,
+    time: f64,
+    rng: StdRng,
+}
+
+/// Headless kinematic simulator of a differential drive robot on a `GridMap`
+///
+/// The velocity commands from [`arci::MoveBase`] are applied by [`RobotSimulator::step`] with
+/// the acceleration limits. `y` of the commands is ignored. The robot stops when it hits
+/// `Cell::Obstacle` or goes out of the map. [`arci::Localization`] returns the true pose.
+#[derive(Debug)]
+pub struct RobotSimulator {
+    config: RobotSimulatorConfig,
+    map: GridMap<u8>,
+    state: Mutex<SimulatorState>,
+}
+
+impl RobotSimulator {
+    pub fn new(map: GridMap<u8>, initial_pose: Pose, config: RobotSimulatorConfig) -> Result<Self> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Ok(Self {
+            config,
+            map,
+            state: Mutex::new(SimulatorState {
+                pose: initial_pose,
+                odometry: initial_pose,
+                velocity: Velocity::default(),
+                command: Velocity::default(),
+                collided: false,
+                time: 0.0,
+                rng,
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &RobotSimulatorConfig {
+        &self.config
+    }
+
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    /// True pose of the robot
+    pub fn pose(&self) -> Pose {
+        self.state.lock().unwrap().pose
+    }
+
+    /// Pose integrated from the velocity without the slip, which starts from the initial pose
+    pub fn odometry(&self) -> Pose {
+        self.state.lock().unwrap().odometry
+    }
+
+    pub fn velocity(&self) -> Velocity {
+        self.state.lock().unwrap().velocity
+    }
+
+    /// Whether the robot has hit an obstacle
+    pub fn is_collided(&self) -> bool {
+        self.state.lock().unwrap().collided
+    }
+
+    /// Elapsed time of the simulation [s]
+    pub fn time(&self) -> f64 {
+        self.state.lock().unwrap().time
+    }
+
+    /// Move the robot to the pose, stop it and clear the collision
+    pub fn reset(&self, pose: Pose) {
+        let mut state = self.state.lock().unwrap();
+        state.pose = pose;
+        state.odometry = pose;
+        state.velocity = Velocity::default();
+        state.command = Velocity::default();
+        state.collided = false;
+    }
+
+    /// Whether the robot collides at the position
+    pub fn is_collision(&self, x: f64, y: f64) -> bool {
+        let radius = self.config.robot_radius;
+        let resolution = self.map.resolution();
+        let Some(center) = self.map.to_grid(x, y) else {
+            return true;
+        };
+        let cells = (radius / resolution).ceil() as usize;
+        for gy in center.y.saturating_sub(cells)..=center.y + cells {
+            for gx in center.x.saturating_sub(cells)..=center.x + cells {
+                let grid = Grid::new(gx, gy);
+                let Some(Position { x: cx, y: cy }) = self.map.to_position(&grid) else {
+                    continue;
+                };
+                if (cx - x).hypot(cy - y) <= radius
+                    && matches!(self.map.cell(&grid), Some(Cell::Obstacle))
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Advance the simulation by `dt` [s]
+    pub fn step(&self, dt: f64) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.time += dt;
+        if state.collided {
+            state.velocity = Velocity::default();
+            return;
+        }
+        let [max_x, max_theta] = self.config.max_velocity;
+        let [accel_x, accel_theta] = self.config.max_acceleration;
+        let accelerate = |current: f64, command: f64, max_velocity: f64, max_accel: f64| {
+            let command = command.clamp(-max_velocity, max_velocity);
+            current + (command - current).clamp(-max_accel * dt, max_accel * dt)
+        };
+        state.velocity = Velocity {
+            x: accelerate(state.velocity.x, state.command.x, max_x, accel_x),
+            y: 0.0,
+            theta: accelerate(
+                state.velocity.theta,
+                state.command.theta,
+                max_theta,
+                accel_theta,
+            ),
+        };
+        let slip_x = 1.0 + sample_normal(&mut state.rng, self.config.slip_std_dev);
+        let slip_theta = 1.0 + sample_normal(&mut state.rng, self.config.slip_std_dev);
+        let slipped = Velocity {
+            x: state.velocity.x * slip_x,
+            y: 0.0,
+            theta: state.velocity.theta * slip_theta,
+        };
+        // check the poses along the motion by the half of the cells, not to go through the walls
+        let distance = slipped.x.abs() * dt;
+        let num_steps = (distance / (self.map.resolution() / 2.0)).ceil().max(1.0) as usize;
+        let collided = (1..=num_steps).any(|i| {
+            let pose = state.pose * integrate_velocity(&slipped, dt * i as f64 / num_steps as f64);
+            self.is_collision(pose.translation.x, pose.translation.y)
+        });
+        let pose = state.pose * integrate_velocity(&slipped, dt);
+        if collided {
+            state.collided = true;
+            state.velocity = Velocity::default();
+            return;
+        }
+        state.pose = pose;
+        state.odometry *= integrate_velocity(&state.velocity, dt);
+    }
+}
+
+impl arci::MoveBase for RobotSimulator {
+    fn send_velocity(&self, velocity: &arci::BaseVelocity) -> std::result::Result<(), arci::Error> {
+        self.state.lock().unwrap().command = Velocity {
+            x: velocity.x,
+            y: 0.0,
+            theta: velocity.theta,
+        };
+        Ok(())
+    }
+
+    fn current_velocity(&self) -> std::result::Result<arci::BaseVelocity, arci::Error> {
+        let velocity = self.velocity();
+        Ok(arci::BaseVelocity::new(
+            velocity.x,
+            velocity.y,
+            velocity.theta,
+        ))
+    }
+}
+
+impl arci::Localization for RobotSimulator {
+    fn current_pose(
+        &self,
+        _frame_id: &str,
+    ) -> std::result::Result<arci::Isometry2<f64>, arci::Error> {
+        Ok(to_arci_pose(&self.pose()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{goal_distance_map, obstacle_distance_map, path_distance_map, DwaPlanner, Limits};
+    use arci::{Localization, MoveBase};
+    use grid_map::LayeredGridMap;
+    use nalgebra as na;
+    use std::collections::HashMap;
+
+    fn new_map() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(-1.0, -1.0), Position::new(3.0, 1.0), 0.05);
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(70, y)).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn robot_simulator_test() {
+        let simulator = RobotSimulator::new(
+            new_map(),
+            Pose::identity(),
+            RobotSimulatorConfig {
+                seed: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        simulator
+            .send_velocity(&arci::BaseVelocity::new(0.5, 0.3, 1.0))
+            .unwrap();
+        // limited by the acceleration
+        simulator.step(0.1);
+        let velocity = simulator.current_velocity().unwrap();
+        assert!((velocity.x - 0.2).abs() < 1e-9);
+        assert_eq!(velocity.y, 0.0);
+        assert!((velocity.theta - 0.5).abs() < 1e-9);
+        for _ in 0..9 {
+            simulator.step(0.1);
+        }
+        assert!((simulator.velocity().x - 0.5).abs() < 1e-9);
+        assert!((simulator.time() - 1.0).abs() < 1e-9);
+        // no slip
+        assert_eq!(simulator.pose(), simulator.odometry());
+
+        // drive along the circle by the constant velocity
+        simulator.reset(Pose::identity());
+        {
+            let mut state = simulator.state.lock().unwrap();
+            state.velocity = Velocity {
+                x: 0.5,
+                y: 0.0,
+                theta: 1.0,
+            };
+            state.command = state.velocity;
+        }
+        for _ in 0..10 {
+            simulator.step(0.1);
+        }
+        let pose = simulator.current_pose("").unwrap();
+        assert!((pose.translation.x - 0.5 * 1.0_f64.sin()).abs() < 1e-9);
+        assert!((pose.translation.y - 0.5 * (1.0 - 1.0_f64.cos())).abs() < 1e-9);
+        assert!((pose.rotation.angle() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn robot_simulator_collision_test() {
+        let simulator = RobotSimulator::new(
+            new_map(),
+            Pose::new(na::Vector2::new(2.0, 0.0), 0.0),
+            RobotSimulatorConfig {
+                slip_std_dev: 0.1,
+                seed: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(simulator.is_collision(2.4, 0.0));
+        assert!(!simulator.is_collision(2.0, 0.0));
+        assert!(simulator.is_collision(-2.0, 0.0));
+        simulator
+            .send_velocity(&arci::BaseVelocity::new(1.0, 0.0, 0.0))
+            .unwrap();
+        for _ in 0..20 {
+            simulator.step(0.1);
+        }
+        assert!(simulator.is_collided());
+        assert_eq!(simulator.velocity(), Velocity::default());
+        let x = simulator.pose().translation.x;
+        assert!(x < 2.33 && x > 2.2, "{x}");
+        // slip
+        assert!(simulator.pose() != simulator.odometry());
+
+        // never go through the wall in a step
+        let start = Pose::new(na::Vector2::new(2.3, 0.0), 0.0);
+        simulator.reset(start);
+        assert!(!simulator.is_collision(2.3, 0.0));
+        assert!(!simulator.is_collision(2.8, 0.0));
+        {
+            let mut state = simulator.state.lock().unwrap();
+            state.velocity = Velocity {
+                x: 1.0,
+                y: 0.0,
+                theta: 0.0,
+            };
+            state.command = state.velocity;
+        }
+        simulator.step(0.5);
+        assert!(simulator.is_collided());
+        assert_eq!(simulator.pose(), start);
+    }
+
+    #[test]
+    fn closed_loop_navigation_test() {
+        let map = new_map();
+        let start = Pose::new(na::Vector2::new(-0.5, 0.2), 0.3);
+        let goal = [2.0, 0.0];
+        let path_grid = (10..=60).map(|x| Grid::new(x, 20)).collect::<Vec<_>>();
+        let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
+        let mut maps = HashMap::new();
+        maps.insert(
+            "path".to_owned(),
+            path_distance_map(&map, &path_grid).unwrap(),
+        );
+        maps.insert(
+            "goal".to_owned(),
+            goal_distance_map(&map, &goal_grid).unwrap(),
+        );
+        maps.insert("obstacle".to_owned(), obstacle_distance_map(&map).unwrap());
+        let maps = LayeredGridMap::new(maps);
+        let mut weights = HashMap::new();
+        weights.insert("path".to_owned(), 0.8);
+        weights.insert("goal".to_owned(), 0.9);
+        weights.insert("obstacle".to_owned(), 0.3);
+        let planner = DwaPlanner::new(
+            Limits {
+                max_velocity: [0.5, 2.0].into(),
+                max_accel: [2.0, 5.0].into(),
+                min_velocity: [0.0, -2.0].into(),
+                min_accel: [-2.0, -5.0].into(),
+            },
+            weights,
+            0.1,
+            1.0,
+            5,
+        );
+        let simulator = RobotSimulator::new(
+            map.clone(),
+            start,
+            RobotSimulatorConfig {
+                slip_std_dev: 0.02,
+                seed: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let mut reached = false;
+        for _ in 0..300 {
+            let pose = simulator.pose();
+            if (pose.translation.vector - na::Vector2::new(goal[0], goal[1])).norm() < 0.1 {
+                reached = true;
+                break;
+            }
+            let plan =
+                planner.plan_local_path(&pose, &simulator.velocity(), &maps, &HashMap::new());
+            simulator
+                .send_velocity(&arci::BaseVelocity::new(
+                    plan.velocity.x,
+                    plan.velocity.y,
+                    plan.velocity.theta,
+                ))
+                .unwrap();
+            simulator.step(planner.controller_dt());
+        }
+        assert!(reached, "{}", simulator.pose());
+        assert!(!simulator.is_collided());
+    }
+}
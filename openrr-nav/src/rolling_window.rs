@@ -0,0 +1,221 @@
+use grid_map::{Cell, Grid, GridMap, Position, Size};
+
+/// Fixed-size local map which follows the robot
+///
+/// The cells are aligned to the grid of the global map whose minimum point is `global_origin`,
+/// so the cells keep their positions when the window shifts.
+#[derive(Debug, Clone)]
+pub struct RollingWindow<T>
+where
+    T: Clone,
+{
+    map: GridMap<T>,
+    global_origin: Position,
+    /// index of the minimum cell in the global grid, which can be negative
+    origin_index: [i64; 2],
+}
+
+impl<T> RollingWindow<T>
+where
+    T: Clone,
+{
+    /// Create the window of `size` cells centered on `center`
+    pub fn new(global_origin: Position, resolution: f64, size: Size, center: [f64; 2]) -> Self {
+        let origin_index = Self::origin_index_for(&global_origin, resolution, &size, center);
+        let map = GridMap::new_with_size(
+            Self::min_point_for(&global_origin, resolution, origin_index),
+            size,
+            resolution,
+        );
+        Self {
+            map,
+            global_origin,
+            origin_index,
+        }
+    }
+
+    /// Create the window aligned to the global map
+    pub fn new_aligned_to(global_map: &GridMap<T>, size: Size, center: [f64; 2]) -> Self {
+        Self::new(
+            global_map.min_point().to_owned(),
+            global_map.resolution(),
+            size,
+            center,
+        )
+    }
+
+    pub fn map(&self) -> &GridMap<T> {
+        &self.map
+    }
+
+    pub fn map_mut(&mut self) -> &mut GridMap<T> {
+        &mut self.map
+    }
+
+    /// Index of the minimum cell of the window in the global grid
+    pub fn origin_index(&self) -> [i64; 2] {
+        self.origin_index
+    }
+
+    /// Move the window to be centered on `center`
+    ///
+    /// The cells which are still in the window keep their data, and the newly exposed cells
+    /// are `Cell::Uninitialized`. Returns the grids of the newly exposed cells.
+    pub fn update_center(&mut self, center: [f64; 2]) -> Vec<Grid> {
+        let resolution = self.map.resolution();
+        let size = Size::new(self.map.width(), self.map.height());
+        let new_origin_index =
+            Self::origin_index_for(&self.global_origin, resolution, &size, center);
+        if new_origin_index == self.origin_index {
+            return vec![];
+        }
+        let dx = new_origin_index[0] - self.origin_index[0];
+        let dy = new_origin_index[1] - self.origin_index[1];
+        let mut new_map = GridMap::new_with_size(
+            Self::min_point_for(&self.global_origin, resolution, new_origin_index),
+            size,
+            resolution,
+        );
+        let mut exposed = vec![];
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let grid = Grid::new(x, y);
+                let old_x = x as i64 + dx;
+                let old_y = y as i64 + dy;
+                let old_cell = if old_x >= 0 && old_y >= 0 {
+                    self.map.cell(&Grid::new(old_x as usize, old_y as usize))
+                } else {
+                    None
+                };
+                match old_cell {
+                    Some(cell) => *new_map.cell_mut(&grid).unwrap() = cell.to_owned(),
+                    None => exposed.push(grid),
+                }
+            }
+        }
+        self.map = new_map;
+        self.origin_index = new_origin_index;
+        exposed
+    }
+
+    /// Copy the cells of the global map in the window
+    ///
+    /// The global map must be aligned to the window. The cells out of the global map are
+    /// `Cell::Unknown`.
+    pub fn copy_from(&mut self, global_map: &GridMap<T>, grids: impl IntoIterator<Item = Grid>) {
+        let resolution = self.map.resolution();
+        let global_offset = [
+            ((self.global_origin.x - global_map.min_point().x) / resolution).round() as i64,
+            ((self.global_origin.y - global_map.min_point().y) / resolution).round() as i64,
+        ];
+        for grid in grids {
+            let global_x = grid.x as i64 + self.origin_index[0] + global_offset[0];
+            let global_y = grid.y as i64 + self.origin_index[1] + global_offset[1];
+            let cell = if global_x >= 0 && global_y >= 0 {
+                global_map
+                    .cell(&Grid::new(global_x as usize, global_y as usize))
+                    .cloned()
+            } else {
+                None
+            };
+            if let Some(c) = self.map.cell_mut(&grid) {
+                *c = cell.unwrap_or(Cell::Unknown);
+            }
+        }
+    }
+
+    /// Copy all the cells of the global map in the window
+    pub fn copy_all_from(&mut self, global_map: &GridMap<T>) {
+        let grids = (0..self.map.height())
+            .flat_map(|y| (0..self.map.width()).map(move |x| Grid::new(x, y)))
+            .collect::<Vec<_>>();
+        self.copy_from(global_map, grids);
+    }
+
+    fn origin_index_for(
+        global_origin: &Position,
+        resolution: f64,
+        size: &Size,
+        center: [f64; 2],
+    ) -> [i64; 2] {
+        [
+            ((center[0] - global_origin.x) / resolution).floor() as i64 - (size.width / 2) as i64,
+            ((center[1] - global_origin.y) / resolution).floor() as i64 - (size.height / 2) as i64,
+        ]
+    }
+
+    fn min_point_for(
+        global_origin: &Position,
+        resolution: f64,
+        origin_index: [i64; 2],
+    ) -> Position {
+        Position::new(
+            global_origin.x + origin_index[0] as f64 * resolution,
+            global_origin.y + origin_index[1] as f64 * resolution,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_global_map() -> GridMap<u8> {
+        let mut map = GridMap::new_with_size(Position::new(-1.0, -1.0), Size::new(40, 40), 0.05);
+        for y in 0..40 {
+            for x in 0..40 {
+                map.set_value(&Grid::new(x, y), (x + y) as u8).unwrap();
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn rolling_window_test() {
+        let global = new_global_map();
+        let mut window = RollingWindow::new_aligned_to(&global, Size::new(10, 10), [0.0, 0.0]);
+        assert_eq!(window.origin_index(), [15, 15]);
+        assert!((window.map().min_point().x + 0.25).abs() < 1e-9);
+        window.copy_all_from(&global);
+        assert_eq!(window.map().value(&Grid::new(0, 0)), Some(30));
+
+        // no shift within the same cell
+        assert!(window.update_center([0.01, 0.01]).is_empty());
+
+        // shift by 2 cells in x and -1 cell in y
+        let exposed = window.update_center([0.11, -0.04]);
+        assert_eq!(window.origin_index(), [17, 14]);
+        assert_eq!(exposed.len(), 10 + 9 * 2);
+        assert!(exposed.iter().all(|g| g.x >= 8 || g.y == 0));
+        // kept cells are the same as the global map
+        assert_eq!(window.map().value(&Grid::new(0, 1)), Some(32));
+        assert_eq!(
+            window.map().cell(&Grid::new(9, 5)),
+            Some(&Cell::Uninitialized)
+        );
+        assert_eq!(
+            window.map().cell(&Grid::new(3, 0)),
+            Some(&Cell::Uninitialized)
+        );
+        window.copy_from(&global, exposed);
+        for y in 0..10 {
+            for x in 0..10 {
+                assert_eq!(
+                    window.map().value(&Grid::new(x, y)),
+                    Some((x + 17 + y + 14) as u8)
+                );
+            }
+        }
+        // positions stay aligned to the global grid
+        let p = Position::new(0.12, 0.07);
+        let local = window.map().to_grid(p.x, p.y).unwrap();
+        let global_grid = global.to_grid(p.x, p.y).unwrap();
+        assert_eq!(window.map().value(&local), global.value(&global_grid));
+
+        // out of the global map
+        window.update_center([-1.0, -1.0]);
+        window.copy_all_from(&global);
+        assert_eq!(window.map().cell(&Grid::new(0, 0)), Some(&Cell::Unknown));
+        assert_eq!(window.map().value(&Grid::new(5, 5)), Some(0));
+    }
+}
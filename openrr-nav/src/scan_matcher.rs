@@ -0,0 +1,469 @@
+use grid_map::{Cell, GridMap};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, LaserScan, LikelihoodField, Pose, Result};
+
+/// Parameters of [`ScanMatcher`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ScanMatcherConfig {
+    /// half size of the search window in x and y [m]
+    pub linear_window: f64,
+    /// half size of the search window in theta [rad]
+    pub angular_window: f64,
+    /// step of the search in theta [rad]
+    pub angular_resolution: f64,
+    /// number of the resolutions of the map, the coarsest one is 2^(levels - 1) times larger
+    pub levels: usize,
+    /// standard deviation of the measurement noise [m]
+    pub sigma: f64,
+    /// maximum distance of the likelihood field [m]
+    pub max_distance: f64,
+    /// pose of the laser on the robot [x, y, theta]
+    pub laser_pose: [f64; 3],
+}
+
+impl Default for ScanMatcherConfig {
+    fn default() -> Self {
+        Self {
+            linear_window: 0.3,
+            angular_window: 20.0_f64.to_radians(),
+            angular_resolution: 1.0_f64.to_radians(),
+            levels: 4,
+            sigma: 0.05,
+            max_distance: 0.5,
+            laser_pose: [0.0; 3],
+        }
+    }
+}
+
+impl ScanMatcherConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (field, value) in [
+            ("angular_resolution", self.angular_resolution),
+            ("sigma", self.sigma),
+            ("max_distance", self.max_distance),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be positive, but {value}"),
+                ));
+            }
+        }
+        for (field, value) in [
+            ("linear_window", self.linear_window),
+            ("angular_window", self.angular_window),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(Error::invalid_config(
+                    field,
+                    format!("must be non-negative, but {value}"),
+                ));
+            }
+        }
+        if !(1..=16).contains(&self.levels) {
+            return Err(Error::invalid_config(
+                "levels",
+                format!("must be in 1..=16, but {}", self.levels),
+            ));
+        }
+        if !self.laser_pose.iter().all(|v| v.is_finite()) {
+            return Err(Error::invalid_config("laser_pose", "must be finite"));
+        }
+        Ok(())
+    }
+}
+
+/// Result of [`ScanMatcher`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanMatchResult {
+    pub pose: Pose,
+    /// mean of the scores of the points, from 0 to 1
+    pub score: f64,
+    /// covariance of the pose in [x, y, theta]
+    pub covariance: na::Matrix3<f64>,
+}
+
+/// Candidate of the search, offsets in the cells and the index of the angle
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    angle: usize,
+    x: i64,
+    y: i64,
+    score: f64,
+}
+
+/// Correlative scan matcher which searches the window around the initial pose
+///
+/// The search is done by branch and bound over the multi-resolution maps, where the score of a
+/// coarse cell is the maximum of the fine cells it covers. The result is the same as the
+/// exhaustive search on the finest map.
+#[derive(Debug, Clone)]
+pub struct ScanMatcher {
+    config: ScanMatcherConfig,
+    /// score maps of the levels, in the same grid as the map
+    levels: Vec<GridMap<f64>>,
+    laser_pose: Pose,
+}
+
+impl ScanMatcher {
+    pub fn new(map: &GridMap<u8>, config: ScanMatcherConfig) -> Result<Self> {
+        config.validate()?;
+        let field = LikelihoodField::new(map, config.max_distance);
+        let denominator = 2.0 * config.sigma.powi(2);
+        let mut finest = field.map().clone();
+        for cell in finest.cells_mut() {
+            if let Cell::Value(d) = cell {
+                *d = (-*d * *d / denominator).exp();
+            }
+        }
+        let mut levels = vec![finest];
+        for level in 1..config.levels {
+            let coarse = sliding_max(&levels[0], 1 << level);
+            levels.push(coarse);
+        }
+        let [x, y, theta] = config.laser_pose;
+        Ok(Self {
+            config,
+            levels,
+            laser_pose: Pose::new(na::Vector2::new(x, y), theta),
+        })
+    }
+
+    pub fn config(&self) -> &ScanMatcherConfig {
+        &self.config
+    }
+
+    /// Score maps from the finest to the coarsest
+    pub fn levels(&self) -> &[GridMap<f64>] {
+        &self.levels
+    }
+
+    /// Match the laser scan around the initial pose of the robot
+    ///
+    /// Returns `None` if the scan has no valid beams.
+    pub fn match_scan(&self, scan: &LaserScan, initial_pose: &Pose) -> Option<ScanMatchResult> {
+        self.match_points(&scan.points_in(&self.laser_pose), initial_pose)
+    }
+
+    /// Match the points in the robot frame around the initial pose of the robot
+    pub fn match_points(
+        &self,
+        points: &[na::Point2<f64>],
+        initial_pose: &Pose,
+    ) -> Option<ScanMatchResult> {
+        if points.is_empty() {
+            return None;
+        }
+        let map = &self.levels[0];
+        let resolution = map.resolution();
+        let linear_window = (self.config.linear_window / resolution).round() as i64;
+        let angular_window =
+            (self.config.angular_window / self.config.angular_resolution).round() as i64;
+        let initial_angle = initial_pose.rotation.angle();
+        let angles = (-angular_window..=angular_window)
+            .map(|i| i as f64 * self.config.angular_resolution)
+            .collect::<Vec<_>>();
+        // cells of the points for each angle without the offset
+        let cells = angles
+            .iter()
+            .map(|angle| {
+                let pose = Pose::new(initial_pose.translation.vector, initial_angle + angle);
+                points
+                    .iter()
+                    .map(|p| {
+                        let p = pose * p;
+                        [
+                            ((p.x - map.min_point().x) / resolution).floor() as i64,
+                            ((p.y - map.min_point().y) / resolution).floor() as i64,
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let top = self.levels.len() - 1;
+        let step = 1 << top;
+        let mut candidates = vec![];
+        for angle in 0..angles.len() {
+            let mut x = -linear_window;
+            while x <= linear_window {
+                let mut y = -linear_window;
+                while y <= linear_window {
+                    candidates.push(self.candidate(top, &cells, angle, x, y));
+                    y += step;
+                }
+                x += step;
+            }
+        }
+        let mut best = Candidate {
+            angle: 0,
+            x: 0,
+            y: 0,
+            score: f64::NEG_INFINITY,
+        };
+        self.branch_and_bound(top, candidates, &cells, linear_window, &mut best);
+
+        let covariance = self.covariance(&best, &cells, &angles, linear_window);
+        Some(ScanMatchResult {
+            pose: Pose::new(
+                initial_pose.translation.vector
+                    + na::Vector2::new(best.x as f64, best.y as f64) * resolution,
+                initial_angle + angles[best.angle],
+            ),
+            score: best.score / points.len() as f64,
+            covariance,
+        })
+    }
+
+    fn candidate(
+        &self,
+        level: usize,
+        cells: &[Vec<[i64; 2]>],
+        angle: usize,
+        x: i64,
+        y: i64,
+    ) -> Candidate {
+        let map = &self.levels[level];
+        let width = map.width() as i64;
+        let height = map.height() as i64;
+        let size = 1 << level;
+        // the window of the coarse cell out of the map may cover the cells in the map
+        let clamp = |c: i64| if c < 0 && c + size > 0 { 0 } else { c };
+        let score = cells[angle]
+            .iter()
+            .map(|[cx, cy]| {
+                let (cx, cy) = (clamp(cx + x), clamp(cy + y));
+                if (0..width).contains(&cx) && (0..height).contains(&cy) {
+                    map.cells()[(cy * width + cx) as usize]
+                        .value()
+                        .copied()
+                        .unwrap_or(0.0)
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+        Candidate { angle, x, y, score }
+    }
+
+    fn branch_and_bound(
+        &self,
+        level: usize,
+        mut candidates: Vec<Candidate>,
+        cells: &[Vec<[i64; 2]>],
+        linear_window: i64,
+        best: &mut Candidate,
+    ) {
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        for candidate in candidates {
+            // the score of the coarse candidate is the upper bound of the finer ones
+            if candidate.score <= best.score {
+                break;
+            }
+            if level == 0 {
+                *best = candidate;
+                break;
+            }
+            let half = 1 << (level - 1);
+            let children = [(0, 0), (half, 0), (0, half), (half, half)]
+                .into_iter()
+                .filter(|(dx, dy)| {
+                    candidate.x + dx <= linear_window && candidate.y + dy <= linear_window
+                })
+                .map(|(dx, dy)| {
+                    self.candidate(
+                        level - 1,
+                        cells,
+                        candidate.angle,
+                        candidate.x + dx,
+                        candidate.y + dy,
+                    )
+                })
+                .collect();
+            self.branch_and_bound(level - 1, children, cells, linear_window, best);
+        }
+    }
+
+    /// Covariance from the scores around the best candidate on the finest map
+    fn covariance(
+        &self,
+        best: &Candidate,
+        cells: &[Vec<[i64; 2]>],
+        angles: &[f64],
+        linear_window: i64,
+    ) -> na::Matrix3<f64> {
+        const RANGE: i64 = 2;
+        let resolution = self.levels[0].resolution();
+        let mut sum = 0.0;
+        let mut mean = na::Vector3::zeros();
+        let mut second_moment = na::Matrix3::zeros();
+        let first_angle = best.angle.saturating_sub(RANGE as usize);
+        for (angle, angle_value) in angles
+            .iter()
+            .enumerate()
+            .skip(first_angle)
+            .take(best.angle + RANGE as usize + 1 - first_angle)
+        {
+            for x in (best.x - RANGE).max(-linear_window)..=(best.x + RANGE).min(linear_window) {
+                for y in (best.y - RANGE).max(-linear_window)..=(best.y + RANGE).min(linear_window)
+                {
+                    let weight = self.candidate(0, cells, angle, x, y).score;
+                    let v = na::Vector3::new(
+                        x as f64 * resolution,
+                        y as f64 * resolution,
+                        *angle_value,
+                    );
+                    sum += weight;
+                    mean += v * weight;
+                    second_moment += v * v.transpose() * weight;
+                }
+            }
+        }
+        // the variance of the discretization
+        let discretization = na::Matrix3::from_diagonal(&na::Vector3::new(
+            resolution.powi(2) / 12.0,
+            resolution.powi(2) / 12.0,
+            self.config.angular_resolution.powi(2) / 12.0,
+        ));
+        if sum <= 0.0 {
+            return discretization;
+        }
+        let mean = mean / sum;
+        second_moment / sum - mean * mean.transpose() + discretization
+    }
+}
+
+/// Maximum of the cells in the window of `size` cells from each cell to +x and +y
+fn sliding_max(map: &GridMap<f64>, size: usize) -> GridMap<f64> {
+    let width = map.width();
+    let height = map.height();
+    let value = |cell: &Cell<f64>| cell.value().copied().unwrap_or(0.0);
+    let mut rows = vec![0.0; map.len()];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * width + x] = (x..(x + size).min(width))
+                .map(|i| value(&map.cells()[y * width + i]))
+                .fold(0.0, f64::max);
+        }
+    }
+    let mut coarse = map.clone();
+    for y in 0..height {
+        for x in 0..width {
+            coarse.cells_mut()[y * width + x] = Cell::Value(
+                (y..(y + size).min(height))
+                    .map(|j| rows[j * width + x])
+                    .fold(0.0, f64::max),
+            );
+        }
+    }
+    coarse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::normalize_angle;
+    use grid_map::{Grid, Position};
+
+    fn new_room() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(4.0, 3.0), 0.05);
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 0)).unwrap();
+            map.set_obstacle(&Grid::new(x, map.height() - 1)).unwrap();
+        }
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(0, y)).unwrap();
+            map.set_obstacle(&Grid::new(map.width() - 1, y)).unwrap();
+        }
+        for x in 50..60 {
+            for y in 20..40 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        map
+    }
+
+    /// Cast the rays from the pose
+    fn scan_at(map: &GridMap<u8>, pose: &Pose) -> LaserScan {
+        let mut scan = LaserScan {
+            angle_min: -std::f64::consts::PI,
+            angle_increment: std::f64::consts::PI / 180.0,
+            range_min: 0.05,
+            range_max: 8.0,
+            ranges: vec![],
+        };
+        for i in 0..360 {
+            let angle = pose.rotation.angle() + scan.angle(i);
+            let mut r = 0.0;
+            while r < scan.range_max {
+                let x = pose.translation.x + r * angle.cos();
+                let y = pose.translation.y + r * angle.sin();
+                match map.to_grid(x, y).and_then(|g| map.cell(&g)) {
+                    Some(Cell::Obstacle) | None => break,
+                    _ => r += 0.01,
+                }
+            }
+            scan.ranges.push(r);
+        }
+        scan
+    }
+
+    #[test]
+    fn scan_matcher_test() {
+        let map = new_room();
+        let truth = Pose::new(na::Vector2::new(1.52, 1.23), 0.3);
+        let scan = scan_at(&map, &truth);
+        let initial = Pose::new(na::Vector2::new(1.7, 1.1), 0.45);
+        let matcher = ScanMatcher::new(&map, ScanMatcherConfig::default()).unwrap();
+        assert_eq!(matcher.levels().len(), 4);
+        let result = matcher.match_scan(&scan, &initial).unwrap();
+        assert!(
+            (result.pose.translation.vector - truth.translation.vector).norm() < 0.05,
+            "{}",
+            result.pose
+        );
+        assert!(normalize_angle(result.pose.rotation.angle() - 0.3).abs() < 0.02);
+        assert!(result.score > 0.8);
+        for i in 0..3 {
+            assert!(result.covariance[(i, i)] > 0.0);
+        }
+        assert!(result.covariance[(0, 0)] < 0.01);
+        assert!(result.covariance[(2, 2)] < 0.01);
+
+        // same as the exhaustive search
+        let exhaustive = ScanMatcher::new(
+            &map,
+            ScanMatcherConfig {
+                levels: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .match_scan(&scan, &initial)
+        .unwrap();
+        assert!((exhaustive.score - result.score).abs() < 1e-9);
+
+        assert!(matcher
+            .match_scan(&LaserScan::default(), &initial)
+            .is_none());
+    }
+
+    #[test]
+    fn scan_matcher_config_test() {
+        let map = new_room();
+        let config = ScanMatcherConfig {
+            levels: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            ScanMatcher::new(&map, config),
+            Err(Error::InvalidConfig { field, .. }) if field == "levels"
+        ));
+        let config: ScanMatcherConfig = serde_yaml::from_str("linear_window: 0.5").unwrap();
+        assert_eq!(config.linear_window, 0.5);
+        assert_eq!(config.levels, 4);
+    }
+}
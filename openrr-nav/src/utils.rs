@@ -37,3 +37,194 @@ pub fn nearest_path_point(path: &[Vec<f64>], target_point: [f64; 2]) -> Option<(
         Some((nearest.0, path[nearest.0].clone()))
     }
 }
+
+/// Index of the point which is `distance` ahead of `start` along the path, or the last point
+pub fn look_ahead_path_point(path: &[Vec<f64>], start: usize, distance: f64) -> usize {
+    let mut index = start.min(path.len().saturating_sub(1));
+    let mut length = 0.;
+    while index + 1 < path.len() && length < distance {
+        let (p0, p1) = (&path[index], &path[index + 1]);
+        length += ((p1[0] - p0[0]).powi(2) + (p1[1] - p0[1]).powi(2)).sqrt();
+        index += 1;
+    }
+    index
+}
+
+/// Normalize the angle into [-pi, pi]
+pub fn normalize_angle(angle: f64) -> f64 {
+    use std::f64::consts::PI;
+    let angle = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    // keep pi instead of -pi for the boundary
+    if angle == -PI {
+        PI
+    } else {
+        angle
+    }
+}
+
+/// Sample from the normal distribution with the mean 0 (Box-Muller transform)
+pub(crate) fn sample_normal<R: rand::Rng + ?Sized>(rng: &mut R, std_dev: f64) -> f64 {
+    if std_dev <= 0.0 {
+        return 0.0;
+    }
+    // avoid ln(0)
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Convert the pose into the one of arci, which may use another version of nalgebra
+pub(crate) fn to_arci_pose(pose: &nalgebra::Isometry2<f64>) -> arci::Isometry2<f64> {
+    arci::Isometry2::new(
+        arci::Vector2::new(pose.translation.x, pose.translation.y),
+        pose.rotation.angle(),
+    )
+}
+
+/// Motion by the constant velocity in the robot frame for `dt` [s]
+pub(crate) fn integrate_velocity(velocity: &crate::Velocity, dt: f64) -> crate::Pose {
+    let angle = velocity.theta * dt;
+    let (x, y) = (velocity.x * dt, velocity.y * dt);
+    let translation = if angle.abs() < 1e-9 {
+        nalgebra::Vector2::new(x, y)
+    } else {
+        let (sin, cos) = ((angle.sin()) / angle, (1.0 - angle.cos()) / angle);
+        nalgebra::Vector2::new(sin * x - cos * y, cos * x + sin * y)
+    };
+    crate::Pose::new(translation, angle)
+}
+
+/// Costs of the straight and the diagonal steps between the grid cells
+pub(crate) const STRAIGHT_STEP_COST: u64 = 10;
+pub(crate) const DIAGONAL_STEP_COST: u64 = 14;
+
+/// 8-neighbors in the map with the cost of the step
+pub(crate) fn grid_neighbors<T: Clone>(
+    map: &grid_map::GridMap<T>,
+    grid: grid_map::Grid,
+) -> impl Iterator<Item = (grid_map::Grid, u64)> {
+    let (width, height) = (map.width(), map.height());
+    [
+        (1, 0, STRAIGHT_STEP_COST),
+        (-1, 0, STRAIGHT_STEP_COST),
+        (0, 1, STRAIGHT_STEP_COST),
+        (0, -1, STRAIGHT_STEP_COST),
+        (1, 1, DIAGONAL_STEP_COST),
+        (1, -1, DIAGONAL_STEP_COST),
+        (-1, 1, DIAGONAL_STEP_COST),
+        (-1, -1, DIAGONAL_STEP_COST),
+    ]
+    .into_iter()
+    .filter_map(move |(dx, dy, cost)| {
+        let x = grid.x.checked_add_signed(dx).filter(|x| *x < width)?;
+        let y = grid.y.checked_add_signed(dy).filter(|y| *y < height)?;
+        Some((grid_map::Grid::new(x, y), cost))
+    })
+}
+
+/// Whether the diagonal step cuts the corner of a cell which is not free
+pub(crate) fn cuts_corner(
+    from: &grid_map::Grid,
+    to: &grid_map::Grid,
+    is_free: impl Fn(&grid_map::Grid) -> bool,
+) -> bool {
+    from.x != to.x
+        && from.y != to.y
+        && !(is_free(&grid_map::Grid::new(to.x, from.y))
+            && is_free(&grid_map::Grid::new(from.x, to.y)))
+}
+
+/// Breadth first search on the 8-neighbors
+///
+/// The cells between the start and the goal must be free, and the diagonal steps cut the corners
+/// of the cells which are not free only if `cut_corners` is true. The start and the goal are
+/// included.
+pub(crate) fn shortest_grid_path<T: Clone>(
+    map: &grid_map::GridMap<T>,
+    start: grid_map::Grid,
+    goal: grid_map::Grid,
+    is_free: impl Fn(&grid_map::Grid) -> bool,
+    cut_corners: bool,
+) -> Option<Vec<grid_map::Grid>> {
+    use grid_map::Grid;
+    let width = map.width();
+    map.cell(&start)?;
+    let mut parents: Vec<Option<Grid>> = vec![None; map.len()];
+    parents[start.y * width + start.x] = Some(start);
+    let mut queue = std::collections::VecDeque::from([start]);
+    while let Some(grid) = queue.pop_front() {
+        if grid == goal {
+            let mut path = vec![goal];
+            let mut current = goal;
+            while current != start {
+                current = parents[current.y * width + current.x]?;
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (neighbor, _) in grid_neighbors(map, grid) {
+            let index = neighbor.y * width + neighbor.x;
+            if parents[index].is_some() || (!cut_corners && cuts_corner(&grid, &neighbor, &is_free))
+            {
+                continue;
+            }
+            if neighbor == goal || is_free(&neighbor) {
+                parents[index] = Some(grid);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn normalize_angle_test() {
+        assert!((normalize_angle(0.5) - 0.5).abs() < 1e-9);
+        assert!((normalize_angle(-0.5) + 0.5).abs() < 1e-9);
+        assert!((normalize_angle(2.0 * PI + 0.5) - 0.5).abs() < 1e-9);
+        assert!((normalize_angle(-2.0 * PI - 0.5) + 0.5).abs() < 1e-9);
+        assert!((normalize_angle(3.1 - (-3.1)) - (6.2 - 2.0 * PI)).abs() < 1e-9);
+        assert!((normalize_angle(PI) - PI).abs() < 1e-9);
+        assert!((normalize_angle(-PI) - PI).abs() < 1e-9);
+        assert!((normalize_angle(5.0 * PI) - PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shortest_grid_path_test() {
+        use grid_map::{Grid, GridMap, Position};
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.5, 0.5), 0.1);
+        for cell in map.cells_mut() {
+            *cell = grid_map::Cell::Value(0);
+        }
+        for (x, y) in [(1, 0), (0, 1)] {
+            map.set_obstacle(&Grid::new(x, y)).unwrap();
+        }
+        let is_free = |grid: &Grid| matches!(map.cell(grid), Some(grid_map::Cell::Value(_)));
+        let (start, goal) = (Grid::new(0, 0), Grid::new(2, 2));
+        assert_eq!(
+            shortest_grid_path(&map, start, goal, is_free, true),
+            Some(vec![start, Grid::new(1, 1), goal])
+        );
+        assert_eq!(shortest_grid_path(&map, start, goal, is_free, false), None);
+    }
+
+    #[test]
+    fn sample_normal_test() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let samples = (0..10000)
+            .map(|_| sample_normal(&mut rng, 2.0))
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let var = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.1);
+        assert!((var.sqrt() - 2.0).abs() < 0.1);
+        assert_eq!(sample_normal(&mut rng, 0.0), 0.0);
+    }
+}